@@ -15,7 +15,10 @@ int main() {
 
     // Create the machine
     scryer_MachineBuilder *machine_builder = scryer_machine_builder_new();
-    scryer_Machine *machine = scryer_machine_builder_build(machine_builder);
+    scryer_Machine *machine = NULL;
+    scryer_Term *exception = NULL;
+    error = scryer_machine_builder_build(machine_builder, &machine, &exception);
+    if (error != SCRYER_ERROR_SUCCESS) exit(1);
     printf("Created the machine\n");
 
     // Consult a module
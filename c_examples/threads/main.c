@@ -0,0 +1,85 @@
+#include <stdlib.h>
+#include <stdio.h>
+#include <stdbool.h>
+#include <string.h>
+#include <pthread.h>
+
+#include <scryer_prolog.h>
+
+#define THREADS 4
+
+// Each thread creates its own machine, so they can run concurrently
+void *run_machine(void *arg) {
+    long id = (long)arg;
+    scryer_Error error;
+
+    char *program =
+    "a(1).\n"
+    "a(2).\n"
+    "a(3).\n";
+
+    scryer_MachineBuilder *machine_builder = scryer_machine_builder_new();
+    scryer_Machine *machine = NULL;
+    scryer_Term *exception = NULL;
+    error = scryer_machine_builder_build(machine_builder, &machine, &exception);
+    if (error != SCRYER_ERROR_SUCCESS) exit(1);
+
+    error = scryer_machine_consult_module_string(machine, "test_module", program);
+    if (error != SCRYER_ERROR_SUCCESS) exit(1);
+
+    scryer_QueryState *query_state = NULL;
+    error = scryer_machine_run_query(machine, "a(A).", &query_state);
+    if (error != SCRYER_ERROR_SUCCESS) exit(1);
+
+    int expected = 1;
+    while (true) {
+        scryer_LeafAnswer *leaf_answer = NULL;
+        error = scryer_query_state_next_answer(query_state, &leaf_answer);
+        if (error != SCRYER_ERROR_SUCCESS) exit(1);
+        if (leaf_answer == NULL) break;
+
+        scryer_Bindings *bindings = NULL;
+        error = scryer_leaf_answer_unwrap_bindings(leaf_answer, &bindings);
+        if (error != SCRYER_ERROR_SUCCESS) exit(1);
+
+        scryer_Term *term = NULL;
+        error = scryer_bindings_get(bindings, "A", &term);
+        if (error != SCRYER_ERROR_SUCCESS) exit(1);
+
+        char *big_integer = NULL;
+        error = scryer_term_unwrap_integer(term, &big_integer);
+        if (error != SCRYER_ERROR_SUCCESS) exit(1);
+
+        // Every thread should see exactly the answers of its own machine
+        char expected_str[16];
+        snprintf(expected_str, sizeof(expected_str), "%d", expected);
+        if (strcmp(big_integer, expected_str) != 0) exit(1);
+        printf("Thread %ld: A = %s\n", id, big_integer);
+        expected++;
+
+        scryer_string_drop(big_integer);
+        scryer_term_drop(term);
+        scryer_bindings_drop(bindings);
+        scryer_leaf_answer_drop(leaf_answer);
+    }
+    if (expected != 4) exit(1);
+
+    scryer_query_state_drop(query_state);
+    scryer_machine_drop(machine);
+
+    return NULL;
+}
+
+int main() {
+    pthread_t threads[THREADS];
+
+    for (long i = 0; i < THREADS; i++) {
+        if (pthread_create(&threads[i], NULL, run_machine, (void *)i) != 0) exit(1);
+    }
+
+    for (long i = 0; i < THREADS; i++) {
+        pthread_join(threads[i], NULL);
+    }
+
+    printf("All threads finished\n");
+}
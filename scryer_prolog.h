@@ -3,26 +3,81 @@
 #include <stdint.h>
 #include <stdlib.h>
 
+/**
+ * An error that can be returned from this API.
+ */
 typedef enum scryer_Error {
+  /**
+   * The call succeeded.
+   */
   SCRYER_ERROR_SUCCESS,
+  /**
+   * The call failed.
+   */
   SCRYER_ERROR_ERROR,
+  /**
+   * The [`Machine`] has a query in progress, so it can't be used.
+   */
+  SCRYER_ERROR_BUSY,
 } scryer_Error;
 
+/**
+ * The kind of a leaf answer.
+ */
 typedef enum scryer_LeafAnswerKind {
+  /**
+   * The query succeeded without bindings.
+   */
   SCRYER_LEAF_ANSWER_KIND_TRUE,
+  /**
+   * The query failed.
+   */
   SCRYER_LEAF_ANSWER_KIND_FALSE,
+  /**
+   * The query succeeded with bindings.
+   */
   SCRYER_LEAF_ANSWER_KIND_LEAF_ANSWER,
+  /**
+   * An exception occurred.
+   */
   SCRYER_LEAF_ANSWER_KIND_EXCEPTION,
 } scryer_LeafAnswerKind;
 
+/**
+ * The kind of a term.
+ */
 typedef enum scryer_TermKind {
+  /**
+   * The term is an integer.
+   */
   SCRYER_TERM_KIND_INTEGER,
+  /**
+   * The term is a rational.
+   */
   SCRYER_TERM_KIND_RATIONAL,
+  /**
+   * The term is a float.
+   */
   SCRYER_TERM_KIND_FLOAT,
+  /**
+   * The term is an atom.
+   */
   SCRYER_TERM_KIND_ATOM,
+  /**
+   * The term is a string.
+   */
   SCRYER_TERM_KIND_STRING,
+  /**
+   * The term is a list.
+   */
   SCRYER_TERM_KIND_LIST,
+  /**
+   * The term is a compound
+   */
   SCRYER_TERM_KIND_COMPOUND,
+  /**
+   * The term is a variable.
+   */
   SCRYER_TERM_KIND_VARIABLE,
 } scryer_TermKind;
 
@@ -38,6 +93,9 @@ typedef struct scryer_LeafAnswer scryer_LeafAnswer;
 
 /**
  * A Scryer Prolog instance.
+ *
+ * Each [`Machine`] is independent from the others, so different ones can be
+ * used concurrently from different threads.
  */
 typedef struct scryer_Machine scryer_Machine;
 
@@ -46,10 +104,16 @@ typedef struct scryer_Machine scryer_Machine;
  */
 typedef struct scryer_MachineBuilder scryer_MachineBuilder;
 
+/**
+ * Options for running a query.
+ */
+typedef struct scryer_QueryOptions scryer_QueryOptions;
+
 /**
  * A handler for an in-progress query.
  *
- * It's parent [`Machine`] shouldn't be accessed while this isn't dropped.
+ * While this isn't dropped, its parent [`Machine`] is busy, and most
+ * functions that take it return [`Error::Busy`].
  */
 typedef struct scryer_QueryState scryer_QueryState;
 
@@ -58,6 +122,18 @@ typedef struct scryer_QueryState scryer_QueryState;
  */
 typedef struct scryer_Term scryer_Term;
 
+/**
+ * A callback that receives the result of
+ * [`scryer_query_state_next_answer_async`].
+ *
+ * `error` and `leaf_answer` are the same as what
+ * [`scryer_query_state_next_answer`] would have returned, and `user_data` is
+ * the pointer that was passed along with the callback.
+ */
+typedef void (*scryer_NextAnswerCallback)(void *user_data,
+                                          enum scryer_Error error,
+                                          struct scryer_LeafAnswer *leaf_answer);
+
 #ifdef __cplusplus
 extern "C" {
 #endif // __cplusplus
@@ -84,66 +160,795 @@ void scryer_machine_builder_drop(struct scryer_MachineBuilder *machine_builder);
  * Creates a [`Machine`] from a [`MachineBuilder`].
  *
  * This consumes the [`MachineBuilder`], so you shouldn't call
- * [`scryer_machine_builder_drop`] after.
+ * [`scryer_machine_builder_drop`] after. On success updates `machine` with a
+ * pointer to the [`Machine`], and `exception` to a null pointer.
+ *
+ * # Errors
+ *
+ * If some flag set with [`scryer_machine_builder_set_flag`] can't be set,
+ * returns [`Error::Error`] and updates `machine` to a null pointer. If setting
+ * the flag threw an exception (for example, a `domain_error` because the flag
+ * doesn't exist), `exception` is updated with a pointer to the exception
+ * [`Term`], which tells which flag was rejected and why. Otherwise it's
+ * updated to a null pointer.
  *
  * # Safety
  *
  * `machine_builder` should point to a [`MachineBuilder`] previously created
  * with [`scryer_machine_builder_new`].
  */
-struct scryer_Machine *scryer_machine_builder_build(struct scryer_MachineBuilder *machine_builder);
+enum scryer_Error scryer_machine_builder_build(struct scryer_MachineBuilder *machine_builder,
+                                               struct scryer_Machine **machine,
+                                               struct scryer_Term **exception);
+
+/**
+ * Sets a Prolog flag in the [`Machine`] that will be built.
+ *
+ * The flags are set in order right after the [`Machine`] is created, like with
+ * [`scryer_machine_set_flag`]. If some flag can't be set,
+ * [`scryer_machine_builder_build`] fails.
+ *
+ * The [`Term`] is copied, so it still needs to be dropped separately.
+ *
+ * # Safety
+ *
+ * - `machine_builder` should point to a [`MachineBuilder`] previously created
+ *   with [`scryer_machine_builder_new`].
+ * - `name` should be a null-terminated UTF-8 encoded string.
+ * - `value` should point to a [`Term`].
+ */
+void scryer_machine_builder_set_flag(struct scryer_MachineBuilder *machine_builder,
+                                     const char *name,
+                                     const struct scryer_Term *value);
 
 /**
  * Drops a [`Machine`].
  *
+ * # Errors
+ *
+ * If there is a query in progress in `machine`, returns [`Error::Busy`] and
+ * `machine` isn't dropped. Drop the [`QueryState`] first.
+ *
  * # Safety
  *
  * `machine` should point to a [`Machine`] previously created with
  * [`scryer_machine_builder_build`].
  */
-void scryer_machine_drop(struct scryer_Machine *machine);
+enum scryer_Error scryer_machine_drop(struct scryer_Machine *machine);
 
 /**
  * Run a query from a string.
  *
  * If no error occurs, `query_state` will be updated with a pointer to a
- * [`QueryState`]. This [`Machine`] shoudn't be accessed again until that
- * [`QueryState`] is dropped with `scryer_query_state_drop`.
+ * [`QueryState`]. This [`Machine`] will be busy until that [`QueryState`] is
+ * dropped with `scryer_query_state_drop`.
  *
  * # Errors
  *
- * Currently this function can't error, but this will probably change.
+ * If there is already a query in progress in `machine`, returns
+ * [`Error::Busy`] and updates `query_state` to a null pointer.
  *
  * # Safety
  *
  * - `machine` should point to a [`Machine`] previously created with
- * [`scryer_machine_builder_build`]. - `query` should be a null-terminated
- * UTF-8 encoded string.
+ *   [`scryer_machine_builder_build`].
+ * - `query` should be a null-terminated
+ *   UTF-8 encoded string.
  */
 enum scryer_Error scryer_machine_run_query(struct scryer_Machine *machine,
                                            const char *query,
                                            struct scryer_QueryState **query_state);
 
+/**
+ * Run a query from a string with some [`QueryOptions`].
+ *
+ * This works like [`scryer_machine_run_query`], but the query respects the
+ * limits set in `query_options`.
+ *
+ * # Errors
+ *
+ * - If there is already a query in progress in `machine`, returns
+ *   [`Error::Busy`] and updates `query_state` to a null pointer.
+ * - If the libraries needed to enforce the limits can't be loaded, returns
+ *   [`Error::Error`] and updates `query_state` to a null pointer.
+ *
+ * # Safety
+ *
+ * - `machine` should point to a [`Machine`] previously created with
+ *   [`scryer_machine_builder_build`].
+ * - `query` should be a null-terminated UTF-8 encoded string.
+ * - `query_options` should point to a [`QueryOptions`] previously created with
+ *   [`scryer_query_options_new`].
+ */
+enum scryer_Error scryer_machine_run_query_with_options(struct scryer_Machine *machine,
+                                                        const char *query,
+                                                        const struct scryer_QueryOptions *query_options,
+                                                        struct scryer_QueryState **query_state);
+
+/**
+ * Runs a query from a string and collects its leaf answers.
+ *
+ * If `query_options` isn't a null pointer, the query respects those options
+ * like with [`scryer_machine_run_query_with_options`]. This runs the query
+ * until it has no more answers or until `max_answers` leaf answers were
+ * collected, whichever comes first. If `max_answers` is 0, there is no limit.
+ * On success updates `answers` with a pointer to a buffer containing pointers
+ * to leaf answers, and `len` to the number of leaf answers in that buffer. The
+ * query is dropped before returning, so `machine` can be used again right
+ * away.
+ *
+ * This buffer needs to be dropped with `scryer_leaf_answer_list_drop`.
+ *
+ * # Errors
+ *
+ * - If there is already a query in progress in `machine`, returns
+ *   [`Error::Busy`], updates `answers` to a null pointer and `len` to 0.
+ * - If the libraries needed by `query_options` can't be loaded, returns
+ *   [`Error::Error`], updates `answers` to a null pointer and `len` to 0.
+ * - If an exception occurs, the query stops there and this returns
+ *   [`Error::Error`]. The leaf answers are still returned, and the last one
+ *   contains the exception.
+ *
+ * # Safety
+ *
+ * - `machine` should point to a [`Machine`] previously created with
+ *   [`scryer_machine_builder_build`].
+ * - `query` should be a null-terminated UTF-8 encoded string.
+ * - `query_options` should be a null pointer or point to a [`QueryOptions`]
+ *   previously created with [`scryer_query_options_new`].
+ */
+enum scryer_Error scryer_machine_query_all(struct scryer_Machine *machine,
+                                           const char *query,
+                                           const struct scryer_QueryOptions *query_options,
+                                           uintptr_t max_answers,
+                                           struct scryer_LeafAnswer ***answers,
+                                           uintptr_t *len);
+
+/**
+ * Runs a query from a string and gets its first leaf answer.
+ *
+ * If `query_options` isn't a null pointer, the query respects those options
+ * like with [`scryer_machine_run_query_with_options`]. The query is dropped
+ * before returning, so `machine` can be used again right away. On success
+ * updates `leaf_answer` with a pointer to a [`LeafAnswer`].
+ *
+ * # Errors
+ *
+ * - If there is already a query in progress in `machine`, returns
+ *   [`Error::Busy`] and updates `leaf_answer` to a null pointer.
+ * - If the libraries needed by `query_options` can't be loaded, returns
+ *   [`Error::Error`] and updates `leaf_answer` to a null pointer.
+ * - If an exception occurs, returns [`Error::Error`] and updates `leaf_answer`
+ *   with a pointer to a [`LeafAnswer`] that contains the exception.
+ *
+ * # Safety
+ *
+ * - `machine` should point to a [`Machine`] previously created with
+ *   [`scryer_machine_builder_build`].
+ * - `query` should be a null-terminated UTF-8 encoded string.
+ * - `query_options` should be a null pointer or point to a [`QueryOptions`]
+ *   previously created with [`scryer_query_options_new`].
+ */
+enum scryer_Error scryer_machine_query_once(struct scryer_Machine *machine,
+                                            const char *query,
+                                            const struct scryer_QueryOptions *query_options,
+                                            struct scryer_LeafAnswer **leaf_answer);
+
+/**
+ * Runs a query from a string and checks if it succeeds.
+ *
+ * If `query_options` isn't a null pointer, the query respects those options
+ * like with [`scryer_machine_run_query_with_options`]. The query is dropped
+ * before returning, so `machine` can be used again right away. On success
+ * updates `succeeds` with whether the query has at least one solution, and
+ * `exception` to a null pointer.
+ *
+ * # Errors
+ *
+ * - If there is already a query in progress in `machine`, returns
+ *   [`Error::Busy`], updates `succeeds` to false and `exception` to a null
+ *   pointer.
+ * - If the libraries needed by `query_options` can't be loaded, returns
+ *   [`Error::Error`], updates `succeeds` to false and `exception` to a null
+ *   pointer.
+ * - If an exception occurs, returns [`Error::Error`], updates `succeeds` to
+ *   false and `exception` with a pointer to the exception [`Term`].
+ *
+ * # Safety
+ *
+ * - `machine` should point to a [`Machine`] previously created with
+ *   [`scryer_machine_builder_build`].
+ * - `query` should be a null-terminated UTF-8 encoded string.
+ * - `query_options` should be a null pointer or point to a [`QueryOptions`]
+ *   previously created with [`scryer_query_options_new`].
+ */
+enum scryer_Error scryer_machine_query_succeeds(struct scryer_Machine *machine,
+                                                const char *query,
+                                                const struct scryer_QueryOptions *query_options,
+                                                bool *succeeds,
+                                                struct scryer_Term **exception);
+
 /**
  * Consults a module from a string.
  *
+ * Calling this again with the same `module` consults `program` as a new
+ * version of that module, like reconsulting a file. The predicates defined in
+ * `program` replace their clauses from the previous version instead of being
+ * added to them. Predicates that were only in the previous version are kept,
+ * since modules can't be unloaded.
+ *
  * # Errors
  *
- * Currently this function can't error, but this will probably change.
+ * If there is a query in progress in `machine`, returns [`Error::Busy`].
  *
  * # Safety
  *
  * - `machine` should point to a [`Machine`] previously created with
- * [`scryer_machine_builder_build`]. - `module` and `program` should both be
- * null-terminated UTF-8 encoded strings.
+ *   [`scryer_machine_builder_build`].
+ * - `module` and `program` should both be
+ *   null-terminated UTF-8 encoded strings.
  */
 enum scryer_Error scryer_machine_consult_module_string(struct scryer_Machine *machine,
                                                        const char *module,
                                                        const char *program);
 
+/**
+ * Consults a file, like `consult/1`.
+ *
+ * Relative paths in `use_module/1` and `include/1` directives of the file are
+ * resolved relative to the file itself.
+ *
+ * # Errors
+ *
+ * - If there is a query in progress in `machine`, returns [`Error::Busy`].
+ * - If the file can't be consulted (for example, because it doesn't exist),
+ *   returns [`Error::Error`].
+ *
+ * # Safety
+ *
+ * - `machine` should point to a [`Machine`] previously created with
+ *   [`scryer_machine_builder_build`].
+ * - `path` should be a null-terminated UTF-8 encoded string.
+ */
+enum scryer_Error scryer_machine_consult_file(struct scryer_Machine *machine, const char *path);
+
+/**
+ * Consults a module from a buffer of clauses.
+ *
+ * This works like [`scryer_machine_consult_module_string`], but takes the
+ * clauses as [`Term`]s instead of as program text. Each clause can be either a
+ * fact or a rule (a `:-/2` compound). Directives (`:-/1` compounds) and
+ * clauses with a module qualified head aren't supported.
+ *
+ * The clauses are written as Prolog text into a single program that is then
+ * consulted, so this costs about the same as calling
+ * [`scryer_machine_consult_module_string`] with that text. If `dynamic` is
+ * true, the program also declares each of their predicates with `dynamic/1`,
+ * so they can be modified later with [`scryer_machine_assertz`] and
+ * [`scryer_machine_retract`].
+ *
+ * To load more clauses than fit in memory at once, consult the first chunk
+ * with `dynamic` set to true, and add the following ones with
+ * [`scryer_machine_assertz_clauses`].
+ *
+ * The terms in `clauses` are copied, so they still need to be dropped
+ * separately.
+ *
+ * # Errors
+ *
+ * - If there is a query in progress in `machine`, returns [`Error::Busy`].
+ * - If some clause is a directive, or its head isn't an atom or a compound or
+ *   is module qualified, returns [`Error::Error`] and nothing is consulted.
+ *
+ * # Safety
+ *
+ * - `machine` should point to a [`Machine`] previously created with
+ *   [`scryer_machine_builder_build`].
+ * - `module` should be a null-terminated UTF-8 encoded string.
+ * - `clauses` should point to a buffer containing `len` pointers to terms. It
+ *   can be a null pointer if `len` is 0.
+ */
+enum scryer_Error scryer_machine_consult_module_terms(struct scryer_Machine *machine,
+                                                      const char *module,
+                                                      const struct scryer_Term *const *clauses,
+                                                      uintptr_t len,
+                                                      bool dynamic);
+
+/**
+ * Adds a clause to the end of its predicate in a module, like `assertz/1`.
+ *
+ * The [`Term`] can be either a fact or a rule (a `:-/2` compound). It's
+ * written as Prolog text and run as an `assertz/1` query, so this costs about
+ * the same as running that query with [`scryer_machine_run_query`].
+ *
+ * On success updates `exception` to a null pointer.
+ *
+ * # Errors
+ *
+ * - If there is a query in progress in `machine`, returns [`Error::Busy`] and
+ *   updates `exception` to a null pointer.
+ * - If the clause can't be added, returns [`Error::Error`] and updates
+ *   `exception` with a pointer to the exception [`Term`]. For example, this
+ *   is a `permission_error` if it would modify a static predicate.
+ *
+ * # Safety
+ *
+ * - `machine` should point to a [`Machine`] previously created with
+ *   [`scryer_machine_builder_build`].
+ * - `module` should be a null-terminated UTF-8 encoded string.
+ * - `clause` should point to a [`Term`].
+ */
+enum scryer_Error scryer_machine_assertz(struct scryer_Machine *machine,
+                                         const char *module,
+                                         const struct scryer_Term *clause,
+                                         struct scryer_Term **exception);
+
+/**
+ * Adds a buffer of clauses to the end of their predicates in a module.
+ *
+ * This works like calling [`scryer_machine_assertz`] with each clause in
+ * order, but adds all of them in a single query, so it's much faster for
+ * many clauses. Each clause can be either a fact or a rule (a `:-/2`
+ * compound). Directives (`:-/1` compounds) and clauses with a module
+ * qualified head aren't supported.
+ *
+ * This can be called repeatedly with chunks of a larger set of clauses, for
+ * example after consulting the first chunk with
+ * [`scryer_machine_consult_module_terms`], so that they don't all need to be
+ * in memory at once. Each call writes its chunk as Prolog text, so it needs
+ * memory for that text.
+ *
+ * The terms in `clauses` are copied, so they still need to be dropped
+ * separately.
+ *
+ * On success updates `exception` to a null pointer.
+ *
+ * # Errors
+ *
+ * - If there is a query in progress in `machine`, returns [`Error::Busy`] and
+ *   updates `exception` to a null pointer.
+ * - If some clause is a directive, or its head isn't an atom or a compound or
+ *   is module qualified, returns [`Error::Error`], updates `exception` to a
+ *   null pointer and nothing is added.
+ * - If some clause can't be added, returns [`Error::Error`] and updates
+ *   `exception` with a pointer to the exception [`Term`]. The clauses before
+ *   it stay added.
+ *
+ * # Safety
+ *
+ * - `machine` should point to a [`Machine`] previously created with
+ *   [`scryer_machine_builder_build`].
+ * - `module` should be a null-terminated UTF-8 encoded string.
+ * - `clauses` should point to a buffer containing `len` pointers to terms. It
+ *   can be a null pointer if `len` is 0.
+ */
+enum scryer_Error scryer_machine_assertz_clauses(struct scryer_Machine *machine,
+                                                 const char *module,
+                                                 const struct scryer_Term *const *clauses,
+                                                 uintptr_t len,
+                                                 struct scryer_Term **exception);
+
+/**
+ * Adds a clause to the start of its predicate in a module, like `asserta/1`.
+ *
+ * This works like [`scryer_machine_assertz`], including its cost.
+ *
+ * On success updates `exception` to a null pointer.
+ *
+ * # Errors
+ *
+ * - If there is a query in progress in `machine`, returns [`Error::Busy`] and
+ *   updates `exception` to a null pointer.
+ * - If the clause can't be added, returns [`Error::Error`] and updates
+ *   `exception` with a pointer to the exception [`Term`]. For example, this
+ *   is a `permission_error` if it would modify a static predicate.
+ *
+ * # Safety
+ *
+ * - `machine` should point to a [`Machine`] previously created with
+ *   [`scryer_machine_builder_build`].
+ * - `module` should be a null-terminated UTF-8 encoded string.
+ * - `clause` should point to a [`Term`].
+ */
+enum scryer_Error scryer_machine_asserta(struct scryer_Machine *machine,
+                                         const char *module,
+                                         const struct scryer_Term *clause,
+                                         struct scryer_Term **exception);
+
+/**
+ * Removes the first clause that unifies with `clause` from a module, like
+ * `retract/1`.
+ *
+ * This works like [`scryer_machine_assertz`], including its cost.
+ *
+ * On success updates `exception` to a null pointer.
+ *
+ * # Errors
+ *
+ * - If there is a query in progress in `machine`, returns [`Error::Busy`] and
+ *   updates `exception` to a null pointer.
+ * - If no clause unifies with `clause`, returns [`Error::Error`] and updates
+ *   `exception` to a null pointer.
+ * - If the clause can't be removed, returns [`Error::Error`] and updates
+ *   `exception` with a pointer to the exception [`Term`]. For example, this
+ *   is a `permission_error` if it's in a static predicate.
+ *
+ * # Safety
+ *
+ * - `machine` should point to a [`Machine`] previously created with
+ *   [`scryer_machine_builder_build`].
+ * - `module` should be a null-terminated UTF-8 encoded string.
+ * - `clause` should point to a [`Term`].
+ */
+enum scryer_Error scryer_machine_retract(struct scryer_Machine *machine,
+                                         const char *module,
+                                         const struct scryer_Term *clause,
+                                         struct scryer_Term **exception);
+
+/**
+ * Sets a Prolog flag, like `set_prolog_flag/2`.
+ *
+ * The [`Term`] is copied, so it still needs to be dropped separately.
+ *
+ * # Errors
+ *
+ * - If there is a query in progress in `machine`, returns [`Error::Busy`].
+ * - If the flag doesn't exist or `value` isn't valid for it, returns
+ *   [`Error::Error`].
+ *
+ * # Safety
+ *
+ * - `machine` should point to a [`Machine`] previously created with
+ *   [`scryer_machine_builder_build`].
+ * - `name` should be a null-terminated UTF-8 encoded string.
+ * - `value` should point to a [`Term`].
+ */
+enum scryer_Error scryer_machine_set_flag(struct scryer_Machine *machine,
+                                          const char *name,
+                                          const struct scryer_Term *value);
+
+/**
+ * Gets the value of a Prolog flag, like `current_prolog_flag/2`.
+ *
+ * On success updates `value` with a pointer to a [`Term`].
+ *
+ * # Errors
+ *
+ * - If there is a query in progress in `machine`, returns [`Error::Busy`] and
+ *   updates `value` to a null pointer.
+ * - If the flag doesn't exist, returns [`Error::Error`] and updates `value`
+ *   to a null pointer.
+ *
+ * # Safety
+ *
+ * - `machine` should point to a [`Machine`] previously created with
+ *   [`scryer_machine_builder_build`].
+ * - `name` should be a null-terminated UTF-8 encoded string.
+ */
+enum scryer_Error scryer_machine_get_flag(struct scryer_Machine *machine,
+                                          const char *name,
+                                          struct scryer_Term **value);
+
+/**
+ * Lists the predicates defined in a module.
+ *
+ * On success updates `predicates` with a pointer to a buffer containing
+ * pointers to terms of the form `Name/Arity`, and `len` to the number of terms
+ * in that buffer, like in [`scryer_term_unwrap_list`].
+ *
+ * This buffer needs to be dropped with `scryer_list_drop`.
+ *
+ * # Errors
+ *
+ * - If there is a query in progress in `machine`, returns [`Error::Busy`],
+ *   updates `predicates` to a null pointer and `len` to 0.
+ * - If the predicates can't be listed, returns [`Error::Error`], updates
+ *   `predicates` to a null pointer and `len` to 0.
+ *
+ * # Safety
+ *
+ * - `machine` should point to a [`Machine`] previously created with
+ *   [`scryer_machine_builder_build`].
+ * - `module` should be a null-terminated UTF-8 encoded string.
+ */
+enum scryer_Error scryer_machine_predicates(struct scryer_Machine *machine,
+                                            const char *module,
+                                            struct scryer_Term ***predicates,
+                                            uintptr_t *len);
+
+/**
+ * Checks if a predicate in a module is dynamic.
+ *
+ * On success updates `dynamic` with whether the predicate `name/arity` is
+ * dynamic.
+ *
+ * # Errors
+ *
+ * - If there is a query in progress in `machine`, returns [`Error::Busy`] and
+ *   updates `dynamic` to false.
+ * - If the predicate doesn't exist, returns [`Error::Error`] and updates
+ *   `dynamic` to false.
+ *
+ * # Safety
+ *
+ * - `machine` should point to a [`Machine`] previously created with
+ *   [`scryer_machine_builder_build`].
+ * - `module` and `name` should both be null-terminated UTF-8 encoded strings.
+ */
+enum scryer_Error scryer_machine_predicate_is_dynamic(struct scryer_Machine *machine,
+                                                      const char *module,
+                                                      const char *name,
+                                                      uintptr_t arity,
+                                                      bool *dynamic);
+
+/**
+ * Gets the clauses of a predicate in a module, like `clause/2`.
+ *
+ * On success updates `clauses` with a pointer to a buffer containing pointers
+ * to the clauses of the predicate `name/arity`, and `len` to the number of
+ * clauses in that buffer, like in [`scryer_term_unwrap_list`]. Facts are
+ * given as their head, and rules as `:-/2` compounds.
+ *
+ * This buffer needs to be dropped with `scryer_list_drop`.
+ *
+ * # Errors
+ *
+ * - If there is a query in progress in `machine`, returns [`Error::Busy`],
+ *   updates `clauses` to a null pointer and `len` to 0.
+ * - If the predicate doesn't exist or its clauses can't be accessed (for
+ *   example, because it's static), returns [`Error::Error`], updates `clauses`
+ *   to a null pointer and `len` to 0.
+ *
+ * # Safety
+ *
+ * - `machine` should point to a [`Machine`] previously created with
+ *   [`scryer_machine_builder_build`].
+ * - `module` and `name` should both be null-terminated UTF-8 encoded strings.
+ */
+enum scryer_Error scryer_machine_predicate_clauses(struct scryer_Machine *machine,
+                                                   const char *module,
+                                                   const char *name,
+                                                   uintptr_t arity,
+                                                   struct scryer_Term ***clauses,
+                                                   uintptr_t *len);
+
+/**
+ * Counts the clauses of a predicate in a module.
+ *
+ * This uses `predicate_property/2` with `number_of_clauses/1`, falling back
+ * to counting the clauses with `clause/2`. On success updates `count` with
+ * the number of clauses of the predicate `name/arity`.
+ *
+ * # Errors
+ *
+ * - If there is a query in progress in `machine`, returns [`Error::Busy`] and
+ *   updates `count` to 0.
+ * - If the predicate doesn't exist or its clauses can't be counted (for
+ *   example, because it's static and can't be accessed with `clause/2`),
+ *   returns [`Error::Error`] and updates `count` to 0.
+ *
+ * # Safety
+ *
+ * - `machine` should point to a [`Machine`] previously created with
+ *   [`scryer_machine_builder_build`].
+ * - `module` and `name` should both be null-terminated UTF-8 encoded strings.
+ */
+enum scryer_Error scryer_machine_predicate_clause_count(struct scryer_Machine *machine,
+                                                        const char *module,
+                                                        const char *name,
+                                                        uintptr_t arity,
+                                                        uintptr_t *count);
+
+/**
+ * Adds or changes an operator, like `op/3`.
+ *
+ * `specifier` is the type of the operator, like `xfx` or `fy`. The operator
+ * is added to the global operator table, so it affects all the code consulted
+ * or queried after this. It doesn't change how [`Term`]s given to this API
+ * are read, since they are always written in canonical form.
+ *
+ * # Errors
+ *
+ * - If there is a query in progress in `machine`, returns [`Error::Busy`].
+ * - If the operator can't be added (for example, because `priority` is
+ *   greater than 1200 or `name` is `,`), returns [`Error::Error`].
+ *
+ * # Safety
+ *
+ * - `machine` should point to a [`Machine`] previously created with
+ *   [`scryer_machine_builder_build`].
+ * - `specifier` and `name` should both be null-terminated UTF-8 encoded
+ *   strings.
+ */
+enum scryer_Error scryer_machine_add_operator(struct scryer_Machine *machine,
+                                              uint32_t priority,
+                                              const char *specifier,
+                                              const char *name);
+
+/**
+ * Removes an operator, like `op/3` with priority 0.
+ *
+ * # Errors
+ *
+ * - If there is a query in progress in `machine`, returns [`Error::Busy`].
+ * - If the operator can't be removed, returns [`Error::Error`].
+ *
+ * # Safety
+ *
+ * - `machine` should point to a [`Machine`] previously created with
+ *   [`scryer_machine_builder_build`].
+ * - `specifier` and `name` should both be null-terminated UTF-8 encoded
+ *   strings.
+ */
+enum scryer_Error scryer_machine_remove_operator(struct scryer_Machine *machine,
+                                                 const char *specifier,
+                                                 const char *name);
+
+/**
+ * Lists the current operators, like `current_op/3`.
+ *
+ * On success updates `operators` with a pointer to a buffer containing
+ * pointers to terms of the form `op(Priority, Specifier, Name)`, and `len` to
+ * the number of terms in that buffer, like in [`scryer_term_unwrap_list`].
+ *
+ * This buffer needs to be dropped with `scryer_list_drop`.
+ *
+ * # Errors
+ *
+ * If there is a query in progress in `machine`, returns [`Error::Busy`],
+ * updates `operators` to a null pointer and `len` to 0.
+ *
+ * # Safety
+ *
+ * `machine` should point to a [`Machine`] previously created with
+ * [`scryer_machine_builder_build`].
+ */
+enum scryer_Error scryer_machine_operators(struct scryer_Machine *machine,
+                                           struct scryer_Term ***operators,
+                                           uintptr_t *len);
+
+/**
+ * Stores a global variable in the blackboard, like `bb_put/2`.
+ *
+ * The value isn't undone on backtracking, and stays until it's replaced by
+ * another call to this or to `bb_put/2`. It can be read from Prolog with
+ * `bb_get/2`. The [`Term`] is copied, so it still needs to be dropped
+ * separately.
+ *
+ * The first call to this or to [`scryer_machine_bb_get`] loads
+ * `library(iso_ext)`, which defines the blackboard predicates. Nothing from it
+ * is imported into `user`.
+ *
+ * # Errors
+ *
+ * - If there is a query in progress in `machine`, returns [`Error::Busy`].
+ * - If the value can't be stored, returns [`Error::Error`].
+ *
+ * # Safety
+ *
+ * - `machine` should point to a [`Machine`] previously created with
+ *   [`scryer_machine_builder_build`].
+ * - `key` should be a null-terminated UTF-8 encoded string.
+ * - `value` should point to a [`Term`].
+ */
+enum scryer_Error scryer_machine_bb_put(struct scryer_Machine *machine,
+                                        const char *key,
+                                        const struct scryer_Term *value);
+
+/**
+ * Gets a global variable from the blackboard, like `bb_get/2`.
+ *
+ * On success updates `value` with a pointer to a [`Term`].
+ *
+ * # Errors
+ *
+ * - If there is a query in progress in `machine`, returns [`Error::Busy`] and
+ *   updates `value` to a null pointer.
+ * - If there is no global variable named `key`, returns [`Error::Error`] and
+ *   updates `value` to a null pointer.
+ *
+ * # Safety
+ *
+ * - `machine` should point to a [`Machine`] previously created with
+ *   [`scryer_machine_builder_build`].
+ * - `key` should be a null-terminated UTF-8 encoded string.
+ */
+enum scryer_Error scryer_machine_bb_get(struct scryer_Machine *machine,
+                                        const char *key,
+                                        struct scryer_Term **value);
+
+/**
+ * Creates a [`QueryOptions`] without any limits.
+ */
+struct scryer_QueryOptions *scryer_query_options_new(void);
+
+/**
+ * Drops a [`QueryOptions`].
+ *
+ * # Safety
+ *
+ * `query_options` should point to a [`QueryOptions`] previously created with
+ * [`scryer_query_options_new`].
+ */
+void scryer_query_options_drop(struct scryer_QueryOptions *query_options);
+
+/**
+ * Sets a wall-clock timeout for the query, in seconds.
+ *
+ * The query is run as with `call_with_time_limit(Seconds, once(Query))`, so
+ * with a timeout it has at most one leaf answer. When the time is up, the
+ * query throws `error(resource_error(time), call_with_time_limit/2)`.
+ *
+ * The first query run with a timeout loads `library(time)` into its
+ * [`Machine`]. Nothing from it is imported into `user`, so it doesn't clash
+ * with the predicates of the program.
+ *
+ * # Errors
+ *
+ * If `seconds` isn't a positive number, returns [`Error::Error`] and the
+ * options aren't changed.
+ *
+ * # Safety
+ *
+ * `query_options` should point to a [`QueryOptions`] previously created with
+ * [`scryer_query_options_new`].
+ */
+enum scryer_Error scryer_query_options_set_timeout(struct scryer_QueryOptions *query_options,
+                                                   double seconds);
+
+/**
+ * Sets a maximum number of inferences for the query.
+ *
+ * The query is run as with `call_with_inference_limit/3`. When the limit is
+ * reached, the query throws
+ * `error(resource_error(inferences), call_with_inference_limit/3)`.
+ *
+ * The first query run with an inference limit loads `library(iso_ext)` into
+ * its [`Machine`]. Nothing from it is imported into `user`, so it doesn't
+ * clash with the predicates of the program.
+ *
+ * # Errors
+ *
+ * If `limit` is 0, returns [`Error::Error`] and the options aren't changed.
+ *
+ * # Safety
+ *
+ * `query_options` should point to a [`QueryOptions`] previously created with
+ * [`scryer_query_options_new`].
+ */
+enum scryer_Error scryer_query_options_set_inference_limit(struct scryer_QueryOptions *query_options,
+                                                           uint64_t limit);
+
+/**
+ * Sets whether the leaf answers of the query include the attributes of its
+ * variables, as residual goals.
+ *
+ * Attributes are what libraries like `library(clpz)`, `library(dif)` and
+ * `library(freeze)` attach to variables to constrain them. This API doesn't
+ * read the attributes themselves. Instead, for each named variable of the
+ * query, it gives the residual goals from `copy_term/3` of the term that
+ * variable is bound to, which are the goals the libraries use to describe
+ * their attributes.
+ *
+ * The goals are given as `copy_term/3` gives them. Some libraries qualify
+ * them with their module, like `clpz:(X in 1..3)`, and then the module that
+ * owns the attribute is known. Others don't, like `dif(X, a)` or
+ * `freeze(X, true)`, and then the module isn't known. They can be unwrapped
+ * with [`scryer_leaf_answer_unwrap_attributes`].
+ *
+ * # Safety
+ *
+ * `query_options` should point to a [`QueryOptions`] previously created with
+ * [`scryer_query_options_new`].
+ */
+void scryer_query_options_set_attributes(struct scryer_QueryOptions *query_options,
+                                         bool attributes);
+
 /**
  * Drops a [`QueryState`].
  *
+ * This ends the query, so its parent [`Machine`] can be used again.
+ *
  * # Safety
  *
  * `query_state` should point to a [`QueryState`] previously created with
@@ -171,13 +976,46 @@ void scryer_query_state_drop(struct scryer_QueryState *query_state);
 enum scryer_Error scryer_query_state_next_answer(struct scryer_QueryState *query_state,
                                                  struct scryer_LeafAnswer **leaf_answer);
 
+/**
+ * Get the next leaf answer from the query in a background thread.
+ *
+ * This returns immediately, and `callback` is called from the background
+ * thread with the result once it's ready, like it would be returned by
+ * [`scryer_query_state_next_answer`]. This is useful for hosts that run an
+ * event loop, which shouldn't be blocked by long running queries. To bound
+ * how long a query can take, use the limits in [`QueryOptions`].
+ *
+ * Each call spawns a new thread that ends after calling `callback`, so this
+ * is meant for queries that take a while to answer. For quick queries the
+ * cost of spawning the thread dominates, and
+ * [`scryer_query_state_next_answer`] is better.
+ *
+ * # Errors
+ *
+ * If `callback` is a null pointer or the thread can't be spawned, returns
+ * [`Error::Error`] and `callback` is never called. In that case `query_state`
+ * can be used again right away.
+ *
+ * # Safety
+ *
+ * - `query_state` should point to a [`QueryState`] previously created with
+ *   [`scryer_machine_run_query`].
+ * - If this succeeds, `query_state` shouldn't be used in any way until
+ *   `callback` is called.
+ * - `callback` and `user_data` should be safe to use from another thread.
+ */
+enum scryer_Error scryer_query_state_next_answer_async(struct scryer_QueryState *query_state,
+                                                       scryer_NextAnswerCallback callback,
+                                                       void *user_data);
+
 /**
  * Drops a [`LeafAnswer`].
  *
  * # Safety
  *
  * `leaf_answer` should point to a [`LeafAnswer`] previously created with
- * [`scryer_query_state_next_answer`].
+ * [`scryer_query_state_next_answer`], [`scryer_machine_query_all`] or
+ * [`scryer_machine_query_once`].
  */
 void scryer_leaf_answer_drop(struct scryer_LeafAnswer *leaf_answer);
 
@@ -187,7 +1025,8 @@ void scryer_leaf_answer_drop(struct scryer_LeafAnswer *leaf_answer);
  * # Safety
  *
  * `leaf_answer` should point to a [`LeafAnswer`] previously created with
- * [`scryer_query_state_next_answer`].
+ * [`scryer_query_state_next_answer`], [`scryer_machine_query_all`] or
+ * [`scryer_machine_query_once`].
  */
 enum scryer_LeafAnswerKind scryer_leaf_answer_kind(const struct scryer_LeafAnswer *leaf_answer);
 
@@ -204,7 +1043,8 @@ enum scryer_LeafAnswerKind scryer_leaf_answer_kind(const struct scryer_LeafAnswe
  * # Safety
  *
  * `leaf_answer` should point to a [`LeafAnswer`] previously created with
- * [`scryer_query_state_next_answer`].
+ * [`scryer_query_state_next_answer`], [`scryer_machine_query_all`] or
+ * [`scryer_machine_query_once`].
  */
 enum scryer_Error scryer_leaf_answer_unwrap_exception(const struct scryer_LeafAnswer *leaf_answer,
                                                       struct scryer_Term **term);
@@ -223,11 +1063,43 @@ enum scryer_Error scryer_leaf_answer_unwrap_exception(const struct scryer_LeafAn
  * # Safety
  *
  * `leaf_answer` should point to a [`LeafAnswer`] previously created with
- * [`scryer_query_state_next_answer`].
+ * [`scryer_query_state_next_answer`], [`scryer_machine_query_all`] or
+ * [`scryer_machine_query_once`].
  */
 enum scryer_Error scryer_leaf_answer_unwrap_bindings(const struct scryer_LeafAnswer *leaf_answer,
                                                      struct scryer_Bindings **bindings);
 
+/**
+ * Unwraps the attributes of a variable of the query from a [`LeafAnswer`].
+ *
+ * On success updates `attributes` with a pointer to a buffer containing
+ * pointers to the residual goals of `variable`, as described in
+ * [`scryer_query_options_set_attributes`], and `len` to the number of goals in
+ * that buffer, like in [`scryer_term_unwrap_list`]. The buffer is empty if the
+ * variable has no attributes.
+ *
+ * This buffer needs to be dropped with `scryer_list_drop`.
+ *
+ * # Errors
+ *
+ * - If the `LeafAnswer` is an exception or false, this returns
+ *   [`Error::Error`], updates `attributes` to a null pointer and `len` to 0.
+ * - If `variable` isn't a named variable of the query, or attributes weren't
+ *   requested with [`scryer_query_options_set_attributes`], this returns
+ *   [`Error::Error`], updates `attributes` to a null pointer and `len` to 0.
+ *
+ * # Safety
+ *
+ * - `leaf_answer` should point to a [`LeafAnswer`] previously created with
+ *   [`scryer_query_state_next_answer`], [`scryer_machine_query_all`] or
+ *   [`scryer_machine_query_once`].
+ * - `variable` should be a null-terminated UTF-8 encoded string.
+ */
+enum scryer_Error scryer_leaf_answer_unwrap_attributes(const struct scryer_LeafAnswer *leaf_answer,
+                                                       const char *variable,
+                                                       struct scryer_Term ***attributes,
+                                                       uintptr_t *len);
+
 /**
  * Drops a [`Bindings`].
  *
@@ -251,9 +1123,9 @@ void scryer_bindings_drop(struct scryer_Bindings *bindings);
  *
  * # Safety
  *
- * - `variable` should be a null-terminated UTF-8 encoded string. -
- * `bindings` should point to a [`bindings`] previously created with
- * [`scryer_leaf_answer_unwrap_bindings`].
+ * - `variable` should be a null-terminated UTF-8 encoded string.
+ * - `bindings` should point to a [`bindings`] previously created with
+ *   [`scryer_leaf_answer_unwrap_bindings`].
  */
 enum scryer_Error scryer_bindings_get(const struct scryer_Bindings *bindings,
                                       const char *variable,
@@ -268,6 +1140,120 @@ enum scryer_Error scryer_bindings_get(const struct scryer_Bindings *bindings,
  */
 void scryer_term_drop(struct scryer_Term *term);
 
+/**
+ * Creates an integer [`Term`].
+ *
+ * `big_integer` is a null-terminated string with the decimal representation
+ * of the integer, so that arbitrary precision can be supported. On success
+ * updates `term` with a pointer to the new [`Term`].
+ *
+ * # Errors
+ *
+ * If `big_integer` isn't a valid integer, returns [`Error::Error`] and updates
+ * `term` to a null pointer.
+ *
+ * # Safety
+ *
+ * `big_integer` should be a null-terminated UTF-8 encoded string.
+ */
+enum scryer_Error scryer_term_new_integer(const char *big_integer, struct scryer_Term **term);
+
+/**
+ * Creates a rational [`Term`].
+ *
+ * `numerator` and `denominator` are strings representing their values, like
+ * in [`scryer_term_new_integer`]. On success updates `term` with a pointer to
+ * the new [`Term`].
+ *
+ * # Errors
+ *
+ * If `numerator` isn't a valid integer or `denominator` isn't a valid
+ * positive integer, returns [`Error::Error`] and updates `term` to a null
+ * pointer.
+ *
+ * # Safety
+ *
+ * `numerator` and `denominator` should both be null-terminated UTF-8 encoded
+ * strings.
+ */
+enum scryer_Error scryer_term_new_rational(const char *numerator,
+                                           const char *denominator,
+                                           struct scryer_Term **term);
+
+/**
+ * Creates a float [`Term`].
+ *
+ * On success updates `term` with a pointer to the new [`Term`].
+ *
+ * # Errors
+ *
+ * If `scryer_float` is infinite or NaN, returns [`Error::Error`] and updates
+ * `term` to a null pointer.
+ *
+ * # Safety
+ *
+ * `term` should be a valid pointer to write the result to.
+ */
+enum scryer_Error scryer_term_new_float(double scryer_float, struct scryer_Term **term);
+
+/**
+ * Creates an atom [`Term`].
+ *
+ * # Safety
+ *
+ * `atom` should be a null-terminated UTF-8 encoded string.
+ */
+struct scryer_Term *scryer_term_new_atom(const char *atom);
+
+/**
+ * Creates a string [`Term`].
+ *
+ * # Safety
+ *
+ * `string` should be a null-terminated UTF-8 encoded string.
+ */
+struct scryer_Term *scryer_term_new_string(const char *string);
+
+/**
+ * Creates a list [`Term`].
+ *
+ * The terms in `term_list` are copied, so they still need to be dropped
+ * separately.
+ *
+ * # Safety
+ *
+ * `term_list` should point to a buffer containing `len` pointers to terms.
+ * It can be a null pointer if `len` is 0.
+ */
+struct scryer_Term *scryer_term_new_list(const struct scryer_Term *const *term_list, uintptr_t len);
+
+/**
+ * Creates a compound [`Term`].
+ *
+ * The terms in `args` are copied, so they still need to be dropped
+ * separately.
+ *
+ * # Safety
+ *
+ * - `functor` should be a null-terminated UTF-8 encoded string.
+ * - `args` should point to a buffer containing `len` pointers to terms.
+ */
+struct scryer_Term *scryer_term_new_compound(const char *functor,
+                                             const struct scryer_Term *const *args,
+                                             uintptr_t len);
+
+/**
+ * Creates a variable [`Term`].
+ *
+ * Variables with the same name in the same term are the same variable. This
+ * includes `_`, which isn't an anonymous variable here.
+ *
+ * # Safety
+ *
+ * `variable` should be a null-terminated UTF-8 encoded string.
+ */
+struct scryer_Term *scryer_term_new_variable(const char *variable);
+
 /**
  * Gets the kind of a [`Term`].
  *
@@ -447,10 +1433,25 @@ void scryer_string_drop(char *string);
  * # Safety
  *
  * `list` should be a list previously created with [`scryer_term_unwrap_list`],
- * and `len` should be it's length.
+ * [`scryer_term_unwrap_compound`], [`scryer_machine_predicates`],
+ * [`scryer_machine_predicate_clauses`], [`scryer_machine_operators`] or
+ * [`scryer_leaf_answer_unwrap_attributes`], and `len` should be it's length.
  */
 void scryer_list_drop(struct scryer_Term **list, uintptr_t len);
 
+/**
+ * Drop a previously allocated list of leaf answers.
+ *
+ * This only frees the memory for the list itself. The leaf answers it
+ * contains should be dropped first separatelly.
+ *
+ * # Safety
+ *
+ * `list` should be a list previously created with
+ * [`scryer_machine_query_all`], and `len` should be it's length.
+ */
+void scryer_leaf_answer_list_drop(struct scryer_LeafAnswer **list, uintptr_t len);
+
 #ifdef __cplusplus
 }  // extern "C"
 #endif  // __cplusplus
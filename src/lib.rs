@@ -4,8 +4,20 @@
 //!
 //! To get started, you need to create a [`MachineBuilder`] to create a
 //! [`Machine`].
+//!
+//! # Threads
+//!
+//! Distinct [`Machine`]s can be created and used concurrently from different
+//! threads. A single [`Machine`], and everything created from it, can be moved
+//! between threads, but should only be used by one thread at a time.
 
-use std::ffi::{CStr, CString, c_char, c_double};
+use std::ffi::{CStr, CString, c_char, c_double, c_void};
+use std::mem::ManuallyDrop;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+mod query;
+mod write;
 
 /// An error that can be returned from this API.
 #[repr(C)]
@@ -14,6 +26,8 @@ pub enum Error {
     Success,
     /// The call failed.
     Error,
+    /// The [`Machine`] has a query in progress, so it can't be used.
+    Busy,
 }
 
 /// The kind of a leaf answer.
@@ -51,15 +65,60 @@ pub enum TermKind {
 }
 
 /// A builder for a [`Machine`].
-pub struct MachineBuilder(scryer_prolog::MachineBuilder);
+pub struct MachineBuilder {
+    inner: scryer_prolog::MachineBuilder,
+    flags: Vec<(String, scryer_prolog::Term)>,
+}
 
 /// A Scryer Prolog instance.
-pub struct Machine(scryer_prolog::Machine);
+///
+/// Each [`Machine`] is independent from the others, so different ones can be
+/// used concurrently from different threads.
+pub struct Machine {
+    // This is owned, but kept as a pointer because the `QueryState` of an
+    // in-progress query holds a mutable reference to it.
+    inner: *mut scryer_prolog::Machine,
+    busy: Arc<AtomicBool>,
+    /// The libraries already loaded by [`Machine::use_library`].
+    libraries: Vec<&'static str>,
+}
+
+// A `Machine` can be moved between threads, which is only sound if the Scryer
+// Prolog machine it owns is `Send`. The same goes for a `QueryState`, which
+// `scryer_query_state_next_answer_async` uses from another thread.
+const _: () = {
+    const fn assert_send<T: Send>() {}
+    assert_send::<scryer_prolog::Machine>();
+    assert_send::<scryer_prolog::QueryState<'static>>();
+    assert_send::<Machine>();
+    assert_send::<QueryState>();
+};
+
+// SAFETY: `inner` is owned by the `Machine`, and the Scryer Prolog machine is
+// `Send`, as asserted above. The only other reference to it is the one in the
+// `QueryState` of an in-progress query, and `busy` keeps the `Machine` from
+// using it until that is dropped.
+unsafe impl Send for Machine {}
 
 /// A handler for an in-progress query.
 ///
-/// It's parent [`Machine`] shouldn't be accessed while this isn't dropped.
-pub struct QueryState<'a>(scryer_prolog::QueryState<'a>);
+/// While this isn't dropped, its parent [`Machine`] is busy, and most
+/// functions that take it return [`Error::Busy`].
+pub struct QueryState {
+    inner: ManuallyDrop<scryer_prolog::QueryState<'static>>,
+    busy: Arc<AtomicBool>,
+    /// The prefix of the variables hidden from the answers, if the query was
+    /// wrapped by [`QueryOptions::wrap_query`].
+    hidden_prefix: Option<String>,
+}
+
+impl Drop for QueryState {
+    fn drop(&mut self) {
+        // The inner query state still uses the machine while being dropped.
+        unsafe { ManuallyDrop::drop(&mut self.inner) };
+        self.busy.store(false, Ordering::Release);
+    }
+}
 
 enum LeafAnswerInner {
     Success(scryer_prolog::LeafAnswer),
@@ -67,7 +126,12 @@ enum LeafAnswerInner {
 }
 
 /// A leaf answer.
-pub struct LeafAnswer(LeafAnswerInner);
+pub struct LeafAnswer(
+    LeafAnswerInner,
+    /// The residual goals of each variable of the query, if they were
+    /// requested with [`QueryOptions`].
+    std::collections::BTreeMap<String, Vec<scryer_prolog::Term>>,
+);
 
 /// A dictionary of bindings in a leaf answer.
 pub struct Bindings(std::collections::BTreeMap<String, scryer_prolog::Term>);
@@ -75,12 +139,574 @@ pub struct Bindings(std::collections::BTreeMap<String, scryer_prolog::Term>);
 /// A Prolog Term.
 pub struct Term(scryer_prolog::Term);
 
+/// A callback that receives the result of
+/// [`scryer_query_state_next_answer_async`].
+///
+/// `error` and `leaf_answer` are the same as what
+/// [`scryer_query_state_next_answer`] would have returned, and `user_data` is
+/// the pointer that was passed along with the callback.
+pub type NextAnswerCallback =
+    Option<extern "C" fn(user_data: *mut c_void, error: Error, leaf_answer: *mut LeafAnswer)>;
+
+/// Everything needed to get the next answer of a query in another thread.
+struct NextAnswerJob {
+    query_state: *mut QueryState,
+    callback: extern "C" fn(user_data: *mut c_void, error: Error, leaf_answer: *mut LeafAnswer),
+    user_data: *mut c_void,
+}
+
+// The Scryer Prolog query state is `Send`, as asserted above, and the caller
+// guarantees that it isn't used by anything else until the callback is called.
+// The callback and the user data are opaque to us, and the caller guarantees
+// that they can be used from another thread.
+unsafe impl Send for NextAnswerJob {}
+
+impl NextAnswerJob {
+    fn run(self) {
+        let (error, leaf_answer) = unsafe { &mut *self.query_state }.next_answer();
+        (self.callback)(self.user_data, error, leaf_answer);
+    }
+}
+
+/// Options for running a query.
+#[derive(Default)]
+pub struct QueryOptions {
+    timeout: Option<f64>,
+    inference_limit: Option<u64>,
+    attributes: bool,
+}
+
+/// Base of the prefix of the variables introduced by this API when wrapping
+/// queries.
+///
+/// It doesn't start with `_`, since Scryer Prolog leaves the bindings of those
+/// variables out of the answers.
+const HIDDEN_VARIABLE_PREFIX: &str = "ScryerCApi";
+
+impl QueryOptions {
+    /// Loads the libraries needed by the goals that wrap queries.
+    fn load_libraries(&self, machine: &mut Machine) -> Error {
+        let mut libraries = Vec::new();
+        if self.timeout.is_some() {
+            libraries.push("time");
+        }
+        if self.inference_limit.is_some() {
+            libraries.push("iso_ext");
+        }
+
+        for library in libraries {
+            match machine.use_library(library) {
+                Error::Success => {}
+                error => return error,
+            }
+        }
+
+        Error::Success
+    }
+
+    /// Wraps the goal of `query` so that it respects these options.
+    ///
+    /// Returns the wrapped query and the prefix of the variables it
+    /// introduces, which isn't the prefix of any variable in `query`. Returns
+    /// `None` if there is nothing to wrap it for.
+    fn wrap_query(&self, query: &str) -> Option<(String, String)> {
+        if self.timeout.is_none() && self.inference_limit.is_none() && !self.attributes {
+            return None;
+        }
+
+        let goal_text = query::goal_text(query);
+        let variables = query::variable_names(goal_text);
+
+        let mut prefix = HIDDEN_VARIABLE_PREFIX.to_string();
+        while variables.iter().any(|v| v.starts_with(&prefix)) {
+            prefix.push('_');
+        }
+
+        // The newline keeps a trailing comment from swallowing what comes after.
+        let mut goal = format!("{goal_text}\n");
+
+        if let Some(limit) = self.inference_limit {
+            goal = format!(
+                "iso_ext:call_with_inference_limit(({goal}), {limit}, {prefix}Result), \
+                 ({prefix}Result == inference_limit_exceeded -> \
+                 throw(error(resource_error(inferences), call_with_inference_limit/3)) ; true)"
+            );
+        }
+
+        if let Some(timeout) = self.timeout {
+            let timeout = write::write_term(&scryer_prolog::Term::Float(timeout));
+            goal = format!(
+                "catch(time:call_with_time_limit({timeout}, once(({goal}))), time_limit_exceeded, \
+                 throw(error(resource_error(time), call_with_time_limit/2)))"
+            );
+        }
+
+        if self.attributes {
+            // Binds `Attributes` to a list of `Name-Goals` pairs, with the
+            // residual goals of each variable.
+            let mut pairs = Vec::new();
+            goal = format!("({goal})");
+            for (i, variable) in variables.iter().enumerate() {
+                goal.push_str(&format!(
+                    ", copy_term({variable}, {variable}, {prefix}Goals{i})"
+                ));
+                let name = write::write_term(&scryer_prolog::Term::Atom(variable.clone()));
+                pairs.push(format!("{name}-{prefix}Goals{i}"));
+            }
+            goal.push_str(&format!(", {prefix}Attributes = [{}]", pairs.join(", ")));
+        }
+
+        goal.push('.');
+        Some((goal, prefix))
+    }
+}
+
+impl Machine {
+    fn new(machine: scryer_prolog::Machine) -> Self {
+        Machine {
+            inner: Box::into_raw(Box::new(machine)),
+            busy: Arc::new(AtomicBool::new(false)),
+            libraries: Vec::new(),
+        }
+    }
+
+    /// Whether there is a query in progress.
+    fn is_busy(&self) -> bool {
+        self.busy.load(Ordering::Acquire)
+    }
+
+    /// Gets the inner machine.
+    ///
+    /// This shouldn't be called while there is a query in progress.
+    fn inner(&mut self) -> &mut scryer_prolog::Machine {
+        debug_assert!(!self.is_busy());
+        unsafe { &mut *self.inner }
+    }
+
+    /// Starts a query.
+    ///
+    /// If there is already a query in progress, returns [`Error::Busy`].
+    fn run_query(&mut self, query: String) -> Result<QueryState, Error> {
+        if self
+            .busy
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(Error::Busy);
+        }
+
+        Ok(QueryState {
+            inner: ManuallyDrop::new(unsafe { &mut *self.inner }.run_query(query)),
+            busy: self.busy.clone(),
+            hidden_prefix: None,
+        })
+    }
+
+    /// Starts a query that respects `query_options`, if there are any.
+    fn run_query_with_options(
+        &mut self,
+        query: &str,
+        query_options: Option<&QueryOptions>,
+    ) -> Result<QueryState, Error> {
+        if self.is_busy() {
+            return Err(Error::Busy);
+        }
+
+        let Some(query_options) = query_options else {
+            return self.run_query(query.into());
+        };
+
+        match query_options.load_libraries(self) {
+            Error::Success => {}
+            error => return Err(error),
+        }
+
+        match query_options.wrap_query(query) {
+            Some((query, hidden_prefix)) => {
+                let mut query_state = self.run_query(query)?;
+                query_state.hidden_prefix = Some(hidden_prefix);
+                Ok(query_state)
+            }
+            None => self.run_query(query.into()),
+        }
+    }
+
+    /// Runs `query` and returns its first answer, discarding the rest.
+    ///
+    /// If there is already a query in progress, returns [`Error::Busy`].
+    fn first_answer(
+        &mut self,
+        query: String,
+    ) -> Result<Option<Result<scryer_prolog::LeafAnswer, scryer_prolog::Term>>, Error> {
+        Ok(self.run_query(query)?.inner.next())
+    }
+
+    /// Runs `goal` and gets the term bound to `variable` in its first answer.
+    ///
+    /// The goal is written as Prolog text and run as a query.
+    fn query_binding(
+        &mut self,
+        goal: &scryer_prolog::Term,
+        variable: &str,
+    ) -> Result<scryer_prolog::Term, Error> {
+        if self.is_busy() {
+            return Err(Error::Busy);
+        }
+
+        let mut query = write::write_term(goal);
+        query.push('.');
+
+        match self.first_answer(query)? {
+            Some(Ok(scryer_prolog::LeafAnswer::LeafAnswer { mut bindings, .. })) => {
+                bindings.remove(variable).ok_or(Error::Error)
+            }
+            _ => Err(Error::Error),
+        }
+    }
+
+    /// Runs `goal` and gets the list bound to `variable` in its first answer.
+    fn query_list(
+        &mut self,
+        goal: &scryer_prolog::Term,
+        variable: &str,
+    ) -> Result<Vec<scryer_prolog::Term>, Error> {
+        match self.query_binding(goal, variable)? {
+            scryer_prolog::Term::List(list) => Ok(list),
+            scryer_prolog::Term::Atom(atom) if atom == "[]" => Ok(Vec::new()),
+            _ => Err(Error::Error),
+        }
+    }
+
+    /// Loads one of the libraries that come with Scryer Prolog.
+    ///
+    /// None of its predicates are imported into `user`, so they have to be
+    /// called module qualified. Each library is only loaded the first time.
+    fn use_library(&mut self, library: &'static str) -> Error {
+        if self.libraries.contains(&library) {
+            return Error::Success;
+        }
+
+        let goal = compound(
+            "use_module",
+            vec![
+                compound("library", vec![atom(library)]),
+                scryer_prolog::Term::List(Vec::new()),
+            ],
+        );
+        let error = self.run_goal(&goal);
+        if let Error::Success = error {
+            self.libraries.push(library);
+        }
+        error
+    }
+
+    /// Runs `goal` once, succeeding if it has at least one solution.
+    fn run_goal(&mut self, goal: &scryer_prolog::Term) -> Error {
+        match self.try_goal(goal) {
+            Ok(()) => Error::Success,
+            Err(GoalError::Busy) => Error::Busy,
+            Err(GoalError::Failure | GoalError::Exception(_)) => Error::Error,
+        }
+    }
+
+    /// Runs `goal` once, telling apart why it didn't succeed.
+    ///
+    /// The goal is written as Prolog text and run as a query.
+    fn try_goal(&mut self, goal: &scryer_prolog::Term) -> Result<(), GoalError> {
+        if self.is_busy() {
+            return Err(GoalError::Busy);
+        }
+
+        let mut query = write::write_term(goal);
+        query.push('.');
+
+        let answer = self.first_answer(query).map_err(|_| GoalError::Busy)?;
+        match answer {
+            Some(Ok(
+                scryer_prolog::LeafAnswer::True | scryer_prolog::LeafAnswer::LeafAnswer { .. },
+            )) => Ok(()),
+            Some(Ok(scryer_prolog::LeafAnswer::Exception(exception)) | Err(exception)) => {
+                Err(GoalError::Exception(exception))
+            }
+            _ => Err(GoalError::Failure),
+        }
+    }
+}
+
+/// Why a goal run with [`Machine::try_goal`] didn't succeed.
+enum GoalError {
+    /// The [`Machine`] has a query in progress.
+    Busy,
+    /// The goal failed.
+    Failure,
+    /// The goal threw an exception.
+    Exception(scryer_prolog::Term),
+}
+
+impl GoalError {
+    /// Gets the [`Error`] to return to C, and the exception term if there is
+    /// one, or a null pointer otherwise.
+    fn into_raw(self) -> (Error, *mut Term) {
+        match self {
+            GoalError::Busy => (Error::Busy, std::ptr::null_mut()),
+            GoalError::Failure => (Error::Error, std::ptr::null_mut()),
+            GoalError::Exception(exception) => {
+                (Error::Error, Box::into_raw(Box::new(Term(exception))))
+            }
+        }
+    }
+}
+
+impl QueryState {
+    /// Gets the next leaf answer, or a null pointer if there are no more.
+    fn next_answer(&mut self) -> (Error, *mut LeafAnswer) {
+        self.inner
+            .next()
+            .map(|l| {
+                let error = if l.is_ok() {
+                    Error::Success
+                } else {
+                    Error::Error
+                };
+                let leaf_answer = LeafAnswer::from_answer(l, self.hidden_prefix.as_deref());
+                (error, Box::into_raw(Box::new(leaf_answer)))
+            })
+            .unwrap_or((Error::Success, std::ptr::null_mut()))
+    }
+
+    /// Gets the first leaf answer, discarding the rest.
+    fn first_leaf_answer(mut self) -> LeafAnswer {
+        match self.inner.next() {
+            Some(answer) => LeafAnswer::from_answer(answer, self.hidden_prefix.as_deref()),
+            None => LeafAnswer(
+                LeafAnswerInner::Success(scryer_prolog::LeafAnswer::False),
+                Default::default(),
+            ),
+        }
+    }
+}
+
+impl LeafAnswer {
+    /// Creates a [`LeafAnswer`] from an answer of a query.
+    ///
+    /// If the query was wrapped, this takes the residual goals out of the
+    /// bindings and hides the bindings of the variables starting with
+    /// `hidden_prefix`. If those were the only bindings the answer becomes
+    /// [`scryer_prolog::LeafAnswer::True`].
+    fn from_answer(
+        answer: Result<scryer_prolog::LeafAnswer, scryer_prolog::Term>,
+        hidden_prefix: Option<&str>,
+    ) -> Self {
+        match answer {
+            Ok(mut la) => {
+                let mut residual_goals = std::collections::BTreeMap::new();
+                if let Some(prefix) = hidden_prefix
+                    && let scryer_prolog::LeafAnswer::LeafAnswer { bindings, .. } = &mut la
+                {
+                    if let Some(scryer_prolog::Term::List(pairs)) =
+                        bindings.remove(&format!("{prefix}Attributes"))
+                    {
+                        residual_goals = pairs
+                            .into_iter()
+                            .filter_map(variable_residual_goals)
+                            .collect();
+                    }
+
+                    bindings.retain(|var, _| !var.starts_with(prefix));
+                    if bindings.is_empty() {
+                        la = scryer_prolog::LeafAnswer::True;
+                    }
+                }
+                LeafAnswer(LeafAnswerInner::Success(la), residual_goals)
+            }
+            Err(error) => LeafAnswer(LeafAnswerInner::Error(error), Default::default()),
+        }
+    }
+
+    fn is_exception(&self) -> bool {
+        matches!(
+            self.0,
+            LeafAnswerInner::Success(scryer_prolog::LeafAnswer::Exception(_))
+                | LeafAnswerInner::Error(_)
+        )
+    }
+}
+
+impl Drop for Machine {
+    fn drop(&mut self) {
+        drop(unsafe { Box::from_raw(self.inner) })
+    }
+}
+
+/// Moves `terms` into a buffer containing pointers to [`Term`]s.
+fn terms_into_raw(terms: Vec<scryer_prolog::Term>) -> (*mut *mut Term, usize) {
+    let mut term_list_vec: Vec<*mut Term> = terms
+        .into_iter()
+        .map(|t| Box::into_raw(Box::new(Term(t))))
+        .collect();
+
+    term_list_vec.shrink_to_fit();
+    assert_eq!(term_list_vec.len(), term_list_vec.capacity());
+
+    let list_ptr = term_list_vec.as_mut_ptr();
+    let len = term_list_vec.len();
+
+    std::mem::forget(term_list_vec);
+
+    (list_ptr, len)
+}
+
+/// Gets the residual goals of a variable from a `Name-Goals` pair.
+fn variable_residual_goals(
+    pair: scryer_prolog::Term,
+) -> Option<(String, Vec<scryer_prolog::Term>)> {
+    let scryer_prolog::Term::Compound(functor, mut args) = pair else {
+        return None;
+    };
+    if functor != "-" || args.len() != 2 {
+        return None;
+    }
+
+    let goals = match args.pop()? {
+        scryer_prolog::Term::List(goals) => goals,
+        scryer_prolog::Term::Atom(atom) if atom == "[]" => Vec::new(),
+        _ => return None,
+    };
+    let scryer_prolog::Term::Atom(name) = args.pop()? else {
+        return None;
+    };
+
+    Some((name, goals))
+}
+
+/// Creates an atom from a C string.
+///
+/// # Safety
+///
+/// `atom` should be a null-terminated UTF-8 encoded string.
+unsafe fn atom_from_raw(atom: *const c_char) -> scryer_prolog::Term {
+    let atom = unsafe { CStr::from_ptr(atom) }
+        .to_str()
+        .expect("UTF-8 encoding");
+
+    scryer_prolog::Term::Atom(atom.into())
+}
+
+/// Creates an atom term.
+fn atom(name: &str) -> scryer_prolog::Term {
+    scryer_prolog::Term::Atom(name.into())
+}
+
+/// Creates a compound term.
+fn compound(functor: &str, args: Vec<scryer_prolog::Term>) -> scryer_prolog::Term {
+    scryer_prolog::Term::Compound(functor.into(), args)
+}
+
+/// Creates a variable term.
+fn var(name: &str) -> scryer_prolog::Term {
+    scryer_prolog::Term::Var(name.into())
+}
+
+/// Creates a predicate indicator term, as in `module:name/arity`.
+fn predicate_indicator(
+    module: scryer_prolog::Term,
+    name: scryer_prolog::Term,
+    arity: scryer_prolog::Term,
+) -> scryer_prolog::Term {
+    compound(":", vec![module, compound("/", vec![name, arity])])
+}
+
+/// Creates a goal that binds `H` to the most general head of the predicate
+/// `module:name/arity`, and fails if there is no such predicate.
+fn predicate_head(
+    module: scryer_prolog::Term,
+    name: scryer_prolog::Term,
+    arity: usize,
+) -> scryer_prolog::Term {
+    let arity = scryer_prolog::Term::Integer(arity.into());
+    compound(
+        ",",
+        vec![
+            compound(
+                "current_predicate",
+                vec![predicate_indicator(module, name.clone(), arity.clone())],
+            ),
+            compound("functor", vec![var("H"), name, arity]),
+        ],
+    )
+}
+
+/// Gets the name and arity of the predicate of `clause`.
+///
+/// Returns `None` if `clause` is a directive, or its head isn't an atom or a
+/// compound or is module qualified.
+fn clause_predicate(clause: &scryer_prolog::Term) -> Option<(&str, usize)> {
+    let head = match clause {
+        scryer_prolog::Term::Compound(f, args) if f == ":-" && args.len() == 2 => &args[0],
+        scryer_prolog::Term::Compound(f, args) if f == ":-" && args.len() == 1 => return None,
+        _ => clause,
+    };
+    match head {
+        scryer_prolog::Term::Compound(f, args) if f == ":" && args.len() == 2 => None,
+        scryer_prolog::Term::Atom(name) => Some((name, 0)),
+        scryer_prolog::Term::Compound(name, args) => Some((name, args.len())),
+        _ => None,
+    }
+}
+
+/// Creates a `set_prolog_flag/2` goal.
+fn set_prolog_flag(name: String, value: scryer_prolog::Term) -> scryer_prolog::Term {
+    scryer_prolog::Term::Compound(
+        "set_prolog_flag".into(),
+        vec![scryer_prolog::Term::Atom(name), value],
+    )
+}
+
+/// Qualifies `term` with `module`, as in `module:term`.
+///
+/// # Safety
+///
+/// `module` should be a null-terminated UTF-8 encoded string.
+unsafe fn module_qualified(
+    module: *const c_char,
+    term: scryer_prolog::Term,
+) -> scryer_prolog::Term {
+    let module = unsafe { CStr::from_ptr(module) }
+        .to_str()
+        .expect("UTF-8 encoding");
+
+    scryer_prolog::Term::Compound(
+        ":".into(),
+        vec![scryer_prolog::Term::Atom(module.into()), term],
+    )
+}
+
+/// Collects `len` terms from a buffer of pointers to [`Term`]s.
+///
+/// # Safety
+///
+/// `terms` should point to a buffer containing `len` pointers to terms. It can
+/// be a null pointer if `len` is 0.
+unsafe fn terms_from_raw(terms: *const &Term, len: usize) -> Vec<scryer_prolog::Term> {
+    if len == 0 {
+        return Vec::new();
+    }
+
+    unsafe { std::slice::from_raw_parts(terms, len) }
+        .iter()
+        .map(|t| t.0.clone())
+        .collect()
+}
+
 // === MachineBuilder methods ===
 
 /// Creates a [`MachineBuilder`] with the default options.
 #[unsafe(no_mangle)]
 pub extern "C" fn scryer_machine_builder_new() -> Box<MachineBuilder> {
-    Box::new(MachineBuilder(scryer_prolog::MachineBuilder::new()))
+    Box::new(MachineBuilder {
+        inner: scryer_prolog::MachineBuilder::new(),
+        flags: Vec::new(),
+    })
 }
 
 /// Drops a [`MachineBuilder`].
@@ -100,7 +726,17 @@ pub unsafe extern "C" fn scryer_machine_builder_drop(machine_builder: Box<Machin
 /// Creates a [`Machine`] from a [`MachineBuilder`].
 ///
 /// This consumes the [`MachineBuilder`], so you shouldn't call
-/// [`scryer_machine_builder_drop`] after.
+/// [`scryer_machine_builder_drop`] after. On success updates `machine` with a
+/// pointer to the [`Machine`], and `exception` to a null pointer.
+///
+/// # Errors
+///
+/// If some flag set with [`scryer_machine_builder_set_flag`] can't be set,
+/// returns [`Error::Error`] and updates `machine` to a null pointer. If setting
+/// the flag threw an exception (for example, a `domain_error` because the flag
+/// doesn't exist), `exception` is updated with a pointer to the exception
+/// [`Term`], which tells which flag was rejected and why. Otherwise it's
+/// updated to a null pointer.
 ///
 /// # Safety
 ///
@@ -109,86 +745,1388 @@ pub unsafe extern "C" fn scryer_machine_builder_drop(machine_builder: Box<Machin
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn scryer_machine_builder_build(
     machine_builder: Box<MachineBuilder>,
-) -> Box<Machine> {
-    Box::new(Machine(machine_builder.0.build()))
+    machine: *mut *mut Machine,
+    exception: *mut *mut Term,
+) -> Error {
+    let MachineBuilder { inner, flags } = *machine_builder;
+
+    let mut new_machine = Machine::new(inner.build());
+    for (name, value) in flags {
+        if let Err(error) = new_machine.try_goal(&set_prolog_flag(name, value)) {
+            let (error, exception_ptr) = error.into_raw();
+            unsafe { *machine = std::ptr::null_mut() };
+            unsafe { *exception = exception_ptr };
+            return error;
+        }
+    }
+
+    unsafe { *machine = Box::into_raw(Box::new(new_machine)) };
+    unsafe { *exception = std::ptr::null_mut() };
+
+    Error::Success
+}
+
+/// Sets a Prolog flag in the [`Machine`] that will be built.
+///
+/// The flags are set in order right after the [`Machine`] is created, like with
+/// [`scryer_machine_set_flag`]. If some flag can't be set,
+/// [`scryer_machine_builder_build`] fails.
+///
+/// The [`Term`] is copied, so it still needs to be dropped separately.
+///
+/// # Safety
+///
+/// - `machine_builder` should point to a [`MachineBuilder`] previously created
+///   with [`scryer_machine_builder_new`].
+/// - `name` should be a null-terminated UTF-8 encoded string.
+/// - `value` should point to a [`Term`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_builder_set_flag(
+    machine_builder: &mut MachineBuilder,
+    name: *const c_char,
+    value: &Term,
+) {
+    let name = unsafe { CStr::from_ptr(name) }
+        .to_str()
+        .expect("UTF-8 encoding");
+
+    machine_builder.flags.push((name.into(), value.0.clone()));
+}
+
+// === Machine methods ===
+
+/// Drops a [`Machine`].
+///
+/// # Errors
+///
+/// If there is a query in progress in `machine`, returns [`Error::Busy`] and
+/// `machine` isn't dropped. Drop the [`QueryState`] first.
+///
+/// # Safety
+///
+/// `machine` should point to a [`Machine`] previously created with
+/// [`scryer_machine_builder_build`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_drop(machine: Box<Machine>) -> Error {
+    if machine.is_busy() {
+        std::mem::forget(machine);
+        return Error::Busy;
+    }
+
+    drop(machine);
+    Error::Success
+}
+
+/// Run a query from a string.
+///
+/// If no error occurs, `query_state` will be updated with a pointer to a
+/// [`QueryState`]. This [`Machine`] will be busy until that [`QueryState`] is
+/// dropped with `scryer_query_state_drop`.
+///
+/// # Errors
+///
+/// If there is already a query in progress in `machine`, returns
+/// [`Error::Busy`] and updates `query_state` to a null pointer.
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `query` should be a null-terminated
+///   UTF-8 encoded string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_run_query(
+    machine: &mut Machine,
+    query: *const c_char,
+    query_state: *mut *mut QueryState,
+) -> Error {
+    let query = unsafe { CStr::from_ptr(query) }.to_str().unwrap();
+
+    let (error, query_state_ptr) = match machine.run_query(query.into()) {
+        Ok(query_state) => (Error::Success, Box::into_raw(Box::new(query_state))),
+        Err(error) => (error, std::ptr::null_mut()),
+    };
+
+    unsafe { *query_state = query_state_ptr };
+
+    error
+}
+
+/// Run a query from a string with some [`QueryOptions`].
+///
+/// This works like [`scryer_machine_run_query`], but the query respects the
+/// limits set in `query_options`.
+///
+/// # Errors
+///
+/// - If there is already a query in progress in `machine`, returns
+///   [`Error::Busy`] and updates `query_state` to a null pointer.
+/// - If the libraries needed to enforce the limits can't be loaded, returns
+///   [`Error::Error`] and updates `query_state` to a null pointer.
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `query` should be a null-terminated UTF-8 encoded string.
+/// - `query_options` should point to a [`QueryOptions`] previously created with
+///   [`scryer_query_options_new`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_run_query_with_options(
+    machine: &mut Machine,
+    query: *const c_char,
+    query_options: &QueryOptions,
+    query_state: *mut *mut QueryState,
+) -> Error {
+    let query = unsafe { CStr::from_ptr(query) }.to_str().unwrap();
+
+    let (error, query_state_ptr) = match machine.run_query_with_options(query, Some(query_options))
+    {
+        Ok(query_state) => (Error::Success, Box::into_raw(Box::new(query_state))),
+        Err(error) => (error, std::ptr::null_mut()),
+    };
+
+    unsafe { *query_state = query_state_ptr };
+
+    error
+}
+
+/// Runs a query from a string and collects its leaf answers.
+///
+/// If `query_options` isn't a null pointer, the query respects those options
+/// like with [`scryer_machine_run_query_with_options`]. This runs the query
+/// until it has no more answers or until `max_answers` leaf answers were
+/// collected, whichever comes first. If `max_answers` is 0, there is no limit.
+/// On success updates `answers` with a pointer to a buffer containing pointers
+/// to leaf answers, and `len` to the number of leaf answers in that buffer. The
+/// query is dropped before returning, so `machine` can be used again right
+/// away.
+///
+/// This buffer needs to be dropped with `scryer_leaf_answer_list_drop`.
+///
+/// # Errors
+///
+/// - If there is already a query in progress in `machine`, returns
+///   [`Error::Busy`], updates `answers` to a null pointer and `len` to 0.
+/// - If the libraries needed by `query_options` can't be loaded, returns
+///   [`Error::Error`], updates `answers` to a null pointer and `len` to 0.
+/// - If an exception occurs, the query stops there and this returns
+///   [`Error::Error`]. The leaf answers are still returned, and the last one
+///   contains the exception.
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `query` should be a null-terminated UTF-8 encoded string.
+/// - `query_options` should be a null pointer or point to a [`QueryOptions`]
+///   previously created with [`scryer_query_options_new`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_query_all(
+    machine: &mut Machine,
+    query: *const c_char,
+    query_options: Option<&QueryOptions>,
+    max_answers: usize,
+    answers: *mut *mut *mut LeafAnswer,
+    len: *mut usize,
+) -> Error {
+    let query = unsafe { CStr::from_ptr(query) }.to_str().unwrap();
+
+    let mut query_state = match machine.run_query_with_options(query, query_options) {
+        Ok(query_state) => query_state,
+        Err(error) => {
+            unsafe { *answers = std::ptr::null_mut() };
+            unsafe { *len = 0 };
+            return error;
+        }
+    };
+
+    let mut error = Error::Success;
+    let mut answers_vec: Vec<*mut LeafAnswer> = Vec::new();
+
+    while max_answers == 0 || answers_vec.len() < max_answers {
+        let Some(answer) = query_state.inner.next() else {
+            break;
+        };
+
+        let leaf_answer = LeafAnswer::from_answer(answer, query_state.hidden_prefix.as_deref());
+        let is_exception = leaf_answer.is_exception();
+        answers_vec.push(Box::into_raw(Box::new(leaf_answer)));
+
+        if is_exception {
+            error = Error::Error;
+            break;
+        }
+    }
+    drop(query_state);
+
+    answers_vec.shrink_to_fit();
+    assert_eq!(answers_vec.len(), answers_vec.capacity());
+
+    let answers_ptr = answers_vec.as_mut_ptr();
+    let answers_len = answers_vec.len();
+
+    std::mem::forget(answers_vec);
+
+    unsafe { *answers = answers_ptr };
+    unsafe { *len = answers_len };
+
+    error
+}
+
+/// Runs a query from a string and gets its first leaf answer.
+///
+/// If `query_options` isn't a null pointer, the query respects those options
+/// like with [`scryer_machine_run_query_with_options`]. The query is dropped
+/// before returning, so `machine` can be used again right away. On success
+/// updates `leaf_answer` with a pointer to a [`LeafAnswer`].
+///
+/// # Errors
+///
+/// - If there is already a query in progress in `machine`, returns
+///   [`Error::Busy`] and updates `leaf_answer` to a null pointer.
+/// - If the libraries needed by `query_options` can't be loaded, returns
+///   [`Error::Error`] and updates `leaf_answer` to a null pointer.
+/// - If an exception occurs, returns [`Error::Error`] and updates `leaf_answer`
+///   with a pointer to a [`LeafAnswer`] that contains the exception.
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `query` should be a null-terminated UTF-8 encoded string.
+/// - `query_options` should be a null pointer or point to a [`QueryOptions`]
+///   previously created with [`scryer_query_options_new`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_query_once(
+    machine: &mut Machine,
+    query: *const c_char,
+    query_options: Option<&QueryOptions>,
+    leaf_answer: *mut *mut LeafAnswer,
+) -> Error {
+    let query = unsafe { CStr::from_ptr(query) }.to_str().unwrap();
+
+    let answer = match machine.run_query_with_options(query, query_options) {
+        Ok(query_state) => query_state.first_leaf_answer(),
+        Err(error) => {
+            unsafe { *leaf_answer = std::ptr::null_mut() };
+            return error;
+        }
+    };
+    let error = if answer.is_exception() {
+        Error::Error
+    } else {
+        Error::Success
+    };
+
+    unsafe { *leaf_answer = Box::into_raw(Box::new(answer)) };
+
+    error
+}
+
+/// Runs a query from a string and checks if it succeeds.
+///
+/// If `query_options` isn't a null pointer, the query respects those options
+/// like with [`scryer_machine_run_query_with_options`]. The query is dropped
+/// before returning, so `machine` can be used again right away. On success
+/// updates `succeeds` with whether the query has at least one solution, and
+/// `exception` to a null pointer.
+///
+/// # Errors
+///
+/// - If there is already a query in progress in `machine`, returns
+///   [`Error::Busy`], updates `succeeds` to false and `exception` to a null
+///   pointer.
+/// - If the libraries needed by `query_options` can't be loaded, returns
+///   [`Error::Error`], updates `succeeds` to false and `exception` to a null
+///   pointer.
+/// - If an exception occurs, returns [`Error::Error`], updates `succeeds` to
+///   false and `exception` with a pointer to the exception [`Term`].
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `query` should be a null-terminated UTF-8 encoded string.
+/// - `query_options` should be a null pointer or point to a [`QueryOptions`]
+///   previously created with [`scryer_query_options_new`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_query_succeeds(
+    machine: &mut Machine,
+    query: *const c_char,
+    query_options: Option<&QueryOptions>,
+    succeeds: *mut bool,
+    exception: *mut *mut Term,
+) -> Error {
+    let query = unsafe { CStr::from_ptr(query) }.to_str().unwrap();
+
+    let answer = match machine.run_query_with_options(query, query_options) {
+        Ok(query_state) => query_state.first_leaf_answer(),
+        Err(error) => {
+            unsafe { *succeeds = false };
+            unsafe { *exception = std::ptr::null_mut() };
+            return error;
+        }
+    };
+
+    let (error, succeeds_val, exception_ptr) = match answer.0 {
+        LeafAnswerInner::Success(
+            scryer_prolog::LeafAnswer::True | scryer_prolog::LeafAnswer::LeafAnswer { .. },
+        ) => (Error::Success, true, std::ptr::null_mut()),
+        LeafAnswerInner::Success(scryer_prolog::LeafAnswer::Exception(e))
+        | LeafAnswerInner::Error(e) => (Error::Error, false, Box::into_raw(Box::new(Term(e)))),
+        LeafAnswerInner::Success(scryer_prolog::LeafAnswer::False) => {
+            (Error::Success, false, std::ptr::null_mut())
+        }
+    };
+
+    unsafe { *succeeds = succeeds_val };
+    unsafe { *exception = exception_ptr };
+
+    error
+}
+
+/// Consults a module from a string.
+///
+/// Calling this again with the same `module` consults `program` as a new
+/// version of that module, like reconsulting a file. The predicates defined in
+/// `program` replace their clauses from the previous version instead of being
+/// added to them. Predicates that were only in the previous version are kept,
+/// since modules can't be unloaded.
+///
+/// # Errors
+///
+/// If there is a query in progress in `machine`, returns [`Error::Busy`].
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `module` and `program` should both be
+///   null-terminated UTF-8 encoded strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_consult_module_string(
+    machine: &mut Machine,
+    module: *const c_char,
+    program: *const c_char,
+) -> Error {
+    let module = unsafe { CStr::from_ptr(module) }
+        .to_str()
+        .expect("UTF-8 encoding");
+    let program = unsafe { CStr::from_ptr(program) }
+        .to_str()
+        .expect("UTF-8 encoding");
+
+    if machine.is_busy() {
+        return Error::Busy;
+    }
+
+    machine.inner().consult_module_string(module, program);
+    Error::Success
+}
+
+/// Consults a file, like `consult/1`.
+///
+/// Relative paths in `use_module/1` and `include/1` directives of the file are
+/// resolved relative to the file itself.
+///
+/// # Errors
+///
+/// - If there is a query in progress in `machine`, returns [`Error::Busy`].
+/// - If the file can't be consulted (for example, because it doesn't exist),
+///   returns [`Error::Error`].
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `path` should be a null-terminated UTF-8 encoded string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_consult_file(
+    machine: &mut Machine,
+    path: *const c_char,
+) -> Error {
+    let path = unsafe { CStr::from_ptr(path) }
+        .to_str()
+        .expect("UTF-8 encoding");
+
+    let goal = scryer_prolog::Term::Compound(
+        "consult".into(),
+        vec![scryer_prolog::Term::Atom(path.into())],
+    );
+    machine.run_goal(&goal)
+}
+
+/// Consults a module from a buffer of clauses.
+///
+/// This works like [`scryer_machine_consult_module_string`], but takes the
+/// clauses as [`Term`]s instead of as program text. Each clause can be either a
+/// fact or a rule (a `:-/2` compound). Directives (`:-/1` compounds) and
+/// clauses with a module qualified head aren't supported.
+///
+/// The clauses are written as Prolog text into a single program that is then
+/// consulted, so this costs about the same as calling
+/// [`scryer_machine_consult_module_string`] with that text. If `dynamic` is
+/// true, the program also declares each of their predicates with `dynamic/1`,
+/// so they can be modified later with [`scryer_machine_assertz`] and
+/// [`scryer_machine_retract`].
+///
+/// To load more clauses than fit in memory at once, consult the first chunk
+/// with `dynamic` set to true, and add the following ones with
+/// [`scryer_machine_assertz_clauses`].
+///
+/// The terms in `clauses` are copied, so they still need to be dropped
+/// separately.
+///
+/// # Errors
+///
+/// - If there is a query in progress in `machine`, returns [`Error::Busy`].
+/// - If some clause is a directive, or its head isn't an atom or a compound or
+///   is module qualified, returns [`Error::Error`] and nothing is consulted.
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `module` should be a null-terminated UTF-8 encoded string.
+/// - `clauses` should point to a buffer containing `len` pointers to terms. It
+///   can be a null pointer if `len` is 0.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_consult_module_terms(
+    machine: &mut Machine,
+    module: *const c_char,
+    clauses: *const &Term,
+    len: usize,
+    dynamic: bool,
+) -> Error {
+    let module = unsafe { CStr::from_ptr(module) }
+        .to_str()
+        .expect("UTF-8 encoding");
+    // SAFETY: The caller guarantees that `clauses` points to `len` terms.
+    let clauses = unsafe { terms_from_raw(clauses, len) };
+
+    if machine.is_busy() {
+        return Error::Busy;
+    }
+
+    let mut predicates = std::collections::BTreeSet::new();
+    for clause in &clauses {
+        match clause_predicate(clause) {
+            Some(predicate) => predicates.insert(predicate),
+            None => return Error::Error,
+        };
+    }
+
+    let mut program = String::new();
+    if dynamic {
+        for (name, arity) in predicates {
+            let indicator = compound(
+                "/",
+                vec![atom(name), scryer_prolog::Term::Integer(arity.into())],
+            );
+            let directive = compound(":-", vec![compound("dynamic", vec![indicator])]);
+            program.push_str(&write::write_term(&directive));
+            program.push_str(".\n");
+        }
+    }
+    for clause in &clauses {
+        program.push_str(&write::write_term(clause));
+        program.push_str(".\n");
+    }
+
+    machine.inner().consult_module_string(module, program);
+    Error::Success
+}
+
+/// Adds a clause to the end of its predicate in a module, like `assertz/1`.
+///
+/// The [`Term`] can be either a fact or a rule (a `:-/2` compound). It's
+/// written as Prolog text and run as an `assertz/1` query, so this costs about
+/// the same as running that query with [`scryer_machine_run_query`].
+///
+/// On success updates `exception` to a null pointer.
+///
+/// # Errors
+///
+/// - If there is a query in progress in `machine`, returns [`Error::Busy`] and
+///   updates `exception` to a null pointer.
+/// - If the clause can't be added, returns [`Error::Error`] and updates
+///   `exception` with a pointer to the exception [`Term`]. For example, this
+///   is a `permission_error` if it would modify a static predicate.
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `module` should be a null-terminated UTF-8 encoded string.
+/// - `clause` should point to a [`Term`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_assertz(
+    machine: &mut Machine,
+    module: *const c_char,
+    clause: &Term,
+    exception: *mut *mut Term,
+) -> Error {
+    // SAFETY: The caller guarantees that `module` is a valid string.
+    let clause = unsafe { module_qualified(module, clause.0.clone()) };
+    let goal = scryer_prolog::Term::Compound("assertz".into(), vec![clause]);
+
+    let (error, exception_ptr) = match machine.try_goal(&goal) {
+        Ok(()) => (Error::Success, std::ptr::null_mut()),
+        Err(error) => error.into_raw(),
+    };
+
+    unsafe { *exception = exception_ptr };
+
+    error
+}
+
+/// Adds a buffer of clauses to the end of their predicates in a module.
+///
+/// This works like calling [`scryer_machine_assertz`] with each clause in
+/// order, but adds all of them in a single query, so it's much faster for
+/// many clauses. Each clause can be either a fact or a rule (a `:-/2`
+/// compound). Directives (`:-/1` compounds) and clauses with a module
+/// qualified head aren't supported.
+///
+/// This can be called repeatedly with chunks of a larger set of clauses, for
+/// example after consulting the first chunk with
+/// [`scryer_machine_consult_module_terms`], so that they don't all need to be
+/// in memory at once. Each call writes its chunk as Prolog text, so it needs
+/// memory for that text.
+///
+/// The terms in `clauses` are copied, so they still need to be dropped
+/// separately.
+///
+/// On success updates `exception` to a null pointer.
+///
+/// # Errors
+///
+/// - If there is a query in progress in `machine`, returns [`Error::Busy`] and
+///   updates `exception` to a null pointer.
+/// - If some clause is a directive, or its head isn't an atom or a compound or
+///   is module qualified, returns [`Error::Error`], updates `exception` to a
+///   null pointer and nothing is added.
+/// - If some clause can't be added, returns [`Error::Error`] and updates
+///   `exception` with a pointer to the exception [`Term`]. The clauses before
+///   it stay added.
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `module` should be a null-terminated UTF-8 encoded string.
+/// - `clauses` should point to a buffer containing `len` pointers to terms. It
+///   can be a null pointer if `len` is 0.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_assertz_clauses(
+    machine: &mut Machine,
+    module: *const c_char,
+    clauses: *const &Term,
+    len: usize,
+    exception: *mut *mut Term,
+) -> Error {
+    // SAFETY: The caller guarantees that `clauses` points to `len` terms.
+    let clauses = unsafe { terms_from_raw(clauses, len) };
+
+    unsafe { *exception = std::ptr::null_mut() };
+
+    if machine.is_busy() {
+        return Error::Busy;
+    }
+
+    if clauses
+        .iter()
+        .any(|clause| clause_predicate(clause).is_none())
+    {
+        return Error::Error;
+    }
+
+    match machine.use_library("lists") {
+        Error::Success => {}
+        error => return error,
+    }
+
+    let clauses = clauses
+        .into_iter()
+        // SAFETY: The caller guarantees that `module` is a valid string.
+        .map(|clause| unsafe { module_qualified(module, clause) })
+        .collect();
+    let goal = compound(
+        ":",
+        vec![
+            atom("lists"),
+            compound(
+                "maplist",
+                vec![atom("assertz"), scryer_prolog::Term::List(clauses)],
+            ),
+        ],
+    );
+
+    let (error, exception_ptr) = match machine.try_goal(&goal) {
+        Ok(()) => (Error::Success, std::ptr::null_mut()),
+        Err(error) => error.into_raw(),
+    };
+
+    unsafe { *exception = exception_ptr };
+
+    error
+}
+
+/// Adds a clause to the start of its predicate in a module, like `asserta/1`.
+///
+/// This works like [`scryer_machine_assertz`], including its cost.
+///
+/// On success updates `exception` to a null pointer.
+///
+/// # Errors
+///
+/// - If there is a query in progress in `machine`, returns [`Error::Busy`] and
+///   updates `exception` to a null pointer.
+/// - If the clause can't be added, returns [`Error::Error`] and updates
+///   `exception` with a pointer to the exception [`Term`]. For example, this
+///   is a `permission_error` if it would modify a static predicate.
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `module` should be a null-terminated UTF-8 encoded string.
+/// - `clause` should point to a [`Term`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_asserta(
+    machine: &mut Machine,
+    module: *const c_char,
+    clause: &Term,
+    exception: *mut *mut Term,
+) -> Error {
+    // SAFETY: The caller guarantees that `module` is a valid string.
+    let clause = unsafe { module_qualified(module, clause.0.clone()) };
+    let goal = scryer_prolog::Term::Compound("asserta".into(), vec![clause]);
+
+    let (error, exception_ptr) = match machine.try_goal(&goal) {
+        Ok(()) => (Error::Success, std::ptr::null_mut()),
+        Err(error) => error.into_raw(),
+    };
+
+    unsafe { *exception = exception_ptr };
+
+    error
+}
+
+/// Removes the first clause that unifies with `clause` from a module, like
+/// `retract/1`.
+///
+/// This works like [`scryer_machine_assertz`], including its cost.
+///
+/// On success updates `exception` to a null pointer.
+///
+/// # Errors
+///
+/// - If there is a query in progress in `machine`, returns [`Error::Busy`] and
+///   updates `exception` to a null pointer.
+/// - If no clause unifies with `clause`, returns [`Error::Error`] and updates
+///   `exception` to a null pointer.
+/// - If the clause can't be removed, returns [`Error::Error`] and updates
+///   `exception` with a pointer to the exception [`Term`]. For example, this
+///   is a `permission_error` if it's in a static predicate.
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `module` should be a null-terminated UTF-8 encoded string.
+/// - `clause` should point to a [`Term`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_retract(
+    machine: &mut Machine,
+    module: *const c_char,
+    clause: &Term,
+    exception: *mut *mut Term,
+) -> Error {
+    // SAFETY: The caller guarantees that `module` is a valid string.
+    let clause = unsafe { module_qualified(module, clause.0.clone()) };
+    let goal = scryer_prolog::Term::Compound("retract".into(), vec![clause]);
+
+    let (error, exception_ptr) = match machine.try_goal(&goal) {
+        Ok(()) => (Error::Success, std::ptr::null_mut()),
+        Err(error) => error.into_raw(),
+    };
+
+    unsafe { *exception = exception_ptr };
+
+    error
+}
+
+/// Sets a Prolog flag, like `set_prolog_flag/2`.
+///
+/// The [`Term`] is copied, so it still needs to be dropped separately.
+///
+/// # Errors
+///
+/// - If there is a query in progress in `machine`, returns [`Error::Busy`].
+/// - If the flag doesn't exist or `value` isn't valid for it, returns
+///   [`Error::Error`].
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `name` should be a null-terminated UTF-8 encoded string.
+/// - `value` should point to a [`Term`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_set_flag(
+    machine: &mut Machine,
+    name: *const c_char,
+    value: &Term,
+) -> Error {
+    let name = unsafe { CStr::from_ptr(name) }
+        .to_str()
+        .expect("UTF-8 encoding");
+
+    machine.run_goal(&set_prolog_flag(name.into(), value.0.clone()))
+}
+
+/// Gets the value of a Prolog flag, like `current_prolog_flag/2`.
+///
+/// On success updates `value` with a pointer to a [`Term`].
+///
+/// # Errors
+///
+/// - If there is a query in progress in `machine`, returns [`Error::Busy`] and
+///   updates `value` to a null pointer.
+/// - If the flag doesn't exist, returns [`Error::Error`] and updates `value`
+///   to a null pointer.
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `name` should be a null-terminated UTF-8 encoded string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_get_flag(
+    machine: &mut Machine,
+    name: *const c_char,
+    value: *mut *mut Term,
+) -> Error {
+    // SAFETY: The caller guarantees that `name` is a valid string.
+    let name = unsafe { atom_from_raw(name) };
+    let goal = compound("current_prolog_flag", vec![name, var("Value")]);
+
+    let (error, value_ptr) = match machine.query_binding(&goal, "Value") {
+        Ok(term) => (Error::Success, Box::into_raw(Box::new(Term(term)))),
+        Err(error) => (error, std::ptr::null_mut()),
+    };
+
+    unsafe { *value = value_ptr };
+
+    error
+}
+
+/// Lists the predicates defined in a module.
+///
+/// On success updates `predicates` with a pointer to a buffer containing
+/// pointers to terms of the form `Name/Arity`, and `len` to the number of terms
+/// in that buffer, like in [`scryer_term_unwrap_list`].
+///
+/// This buffer needs to be dropped with `scryer_list_drop`.
+///
+/// # Errors
+///
+/// - If there is a query in progress in `machine`, returns [`Error::Busy`],
+///   updates `predicates` to a null pointer and `len` to 0.
+/// - If the predicates can't be listed, returns [`Error::Error`], updates
+///   `predicates` to a null pointer and `len` to 0.
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `module` should be a null-terminated UTF-8 encoded string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_predicates(
+    machine: &mut Machine,
+    module: *const c_char,
+    predicates: *mut *mut *mut Term,
+    len: *mut usize,
+) -> Error {
+    // SAFETY: The caller guarantees that `module` is a valid string.
+    let module = unsafe { atom_from_raw(module) };
+    let indicator = compound("/", vec![var("N"), var("A")]);
+    let goal = compound(
+        "findall",
+        vec![
+            indicator,
+            compound(
+                "current_predicate",
+                vec![predicate_indicator(module, var("N"), var("A"))],
+            ),
+            var("List"),
+        ],
+    );
+
+    let (error, predicates_ptr, predicates_len) = match machine.query_list(&goal, "List") {
+        Ok(list) => {
+            let (ptr, len) = terms_into_raw(list);
+            (Error::Success, ptr, len)
+        }
+        Err(error) => (error, std::ptr::null_mut(), 0),
+    };
+
+    unsafe { *predicates = predicates_ptr };
+    unsafe { *len = predicates_len };
+
+    error
+}
+
+/// Checks if a predicate in a module is dynamic.
+///
+/// On success updates `dynamic` with whether the predicate `name/arity` is
+/// dynamic.
+///
+/// # Errors
+///
+/// - If there is a query in progress in `machine`, returns [`Error::Busy`] and
+///   updates `dynamic` to false.
+/// - If the predicate doesn't exist, returns [`Error::Error`] and updates
+///   `dynamic` to false.
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `module` and `name` should both be null-terminated UTF-8 encoded strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_predicate_is_dynamic(
+    machine: &mut Machine,
+    module: *const c_char,
+    name: *const c_char,
+    arity: usize,
+    dynamic: *mut bool,
+) -> Error {
+    // SAFETY: The caller guarantees that `module` and `name` are valid strings.
+    let (module, name) = unsafe { (atom_from_raw(module), atom_from_raw(name)) };
+    let is_dynamic = compound(
+        "predicate_property",
+        vec![
+            compound(":", vec![module.clone(), var("H")]),
+            atom("dynamic"),
+        ],
+    );
+    let goal = compound(
+        ",",
+        vec![
+            predicate_head(module, name, arity),
+            compound(
+                ";",
+                vec![
+                    compound(
+                        "->",
+                        vec![
+                            is_dynamic,
+                            compound("=", vec![var("Dynamic"), atom("true")]),
+                        ],
+                    ),
+                    compound("=", vec![var("Dynamic"), atom("false")]),
+                ],
+            ),
+        ],
+    );
+
+    let (error, dynamic_val) = match machine.query_binding(&goal, "Dynamic") {
+        Ok(scryer_prolog::Term::Atom(dynamic)) => (Error::Success, dynamic == "true"),
+        Ok(_) => (Error::Error, false),
+        Err(error) => (error, false),
+    };
+
+    unsafe { *dynamic = dynamic_val };
+
+    error
+}
+
+/// Gets the clauses of a predicate in a module, like `clause/2`.
+///
+/// On success updates `clauses` with a pointer to a buffer containing pointers
+/// to the clauses of the predicate `name/arity`, and `len` to the number of
+/// clauses in that buffer, like in [`scryer_term_unwrap_list`]. Facts are
+/// given as their head, and rules as `:-/2` compounds.
+///
+/// This buffer needs to be dropped with `scryer_list_drop`.
+///
+/// # Errors
+///
+/// - If there is a query in progress in `machine`, returns [`Error::Busy`],
+///   updates `clauses` to a null pointer and `len` to 0.
+/// - If the predicate doesn't exist or its clauses can't be accessed (for
+///   example, because it's static), returns [`Error::Error`], updates `clauses`
+///   to a null pointer and `len` to 0.
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `module` and `name` should both be null-terminated UTF-8 encoded strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_predicate_clauses(
+    machine: &mut Machine,
+    module: *const c_char,
+    name: *const c_char,
+    arity: usize,
+    clauses: *mut *mut *mut Term,
+    len: *mut usize,
+) -> Error {
+    // SAFETY: The caller guarantees that `module` and `name` are valid strings.
+    let (module, name) = unsafe { (atom_from_raw(module), atom_from_raw(name)) };
+    // Facts are given as their head, and rules as `H :- B`.
+    let clause = compound(
+        ";",
+        vec![
+            compound(
+                "->",
+                vec![
+                    compound("==", vec![var("B"), atom("true")]),
+                    compound("=", vec![var("C"), var("H")]),
+                ],
+            ),
+            compound(
+                "=",
+                vec![var("C"), compound(":-", vec![var("H"), var("B")])],
+            ),
+        ],
+    );
+    let goal = compound(
+        ",",
+        vec![
+            predicate_head(module.clone(), name, arity),
+            compound(
+                "findall",
+                vec![
+                    var("C"),
+                    compound(
+                        ",",
+                        vec![
+                            compound(
+                                "clause",
+                                vec![compound(":", vec![module, var("H")]), var("B")],
+                            ),
+                            clause,
+                        ],
+                    ),
+                    var("List"),
+                ],
+            ),
+        ],
+    );
+
+    let (error, clauses_ptr, clauses_len) = match machine.query_list(&goal, "List") {
+        Ok(list) => {
+            let (ptr, len) = terms_into_raw(list);
+            (Error::Success, ptr, len)
+        }
+        Err(error) => (error, std::ptr::null_mut(), 0),
+    };
+
+    unsafe { *clauses = clauses_ptr };
+    unsafe { *len = clauses_len };
+
+    error
+}
+
+/// Counts the clauses of a predicate in a module.
+///
+/// This uses `predicate_property/2` with `number_of_clauses/1`, falling back
+/// to counting the clauses with `clause/2`. On success updates `count` with
+/// the number of clauses of the predicate `name/arity`.
+///
+/// # Errors
+///
+/// - If there is a query in progress in `machine`, returns [`Error::Busy`] and
+///   updates `count` to 0.
+/// - If the predicate doesn't exist or its clauses can't be counted (for
+///   example, because it's static and can't be accessed with `clause/2`),
+///   returns [`Error::Error`] and updates `count` to 0.
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `module` and `name` should both be null-terminated UTF-8 encoded strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_predicate_clause_count(
+    machine: &mut Machine,
+    module: *const c_char,
+    name: *const c_char,
+    arity: usize,
+    count: *mut usize,
+) -> Error {
+    // SAFETY: The caller guarantees that `module` and `name` are valid strings.
+    let (module, name) = unsafe { (atom_from_raw(module), atom_from_raw(name)) };
+    let head = compound(":", vec![module.clone(), var("H")]);
+    let number_of_clauses = compound(
+        "predicate_property",
+        vec![
+            head.clone(),
+            compound("number_of_clauses", vec![var("Count")]),
+        ],
+    );
+    let count_clauses = compound(
+        ",",
+        vec![
+            compound(
+                "findall",
+                vec![
+                    atom("x"),
+                    compound("clause", vec![head, var("_")]),
+                    var("Clauses"),
+                ],
+            ),
+            compound("length", vec![var("Clauses"), var("Count")]),
+        ],
+    );
+    let goal = compound(
+        ",",
+        vec![
+            predicate_head(module, name, arity),
+            compound(
+                ";",
+                vec![
+                    compound("->", vec![number_of_clauses, atom("true")]),
+                    count_clauses,
+                ],
+            ),
+        ],
+    );
+
+    let (error, count_val) = match machine.query_binding(&goal, "Count") {
+        Ok(scryer_prolog::Term::Integer(int)) => match int.to_string().parse() {
+            Ok(count) => (Error::Success, count),
+            Err(_) => (Error::Error, 0),
+        },
+        Ok(_) => (Error::Error, 0),
+        Err(error) => (error, 0),
+    };
+
+    unsafe { *count = count_val };
+
+    error
+}
+
+/// Adds or changes an operator, like `op/3`.
+///
+/// `specifier` is the type of the operator, like `xfx` or `fy`. The operator
+/// is added to the global operator table, so it affects all the code consulted
+/// or queried after this. It doesn't change how [`Term`]s given to this API
+/// are read, since they are always written in canonical form.
+///
+/// # Errors
+///
+/// - If there is a query in progress in `machine`, returns [`Error::Busy`].
+/// - If the operator can't be added (for example, because `priority` is
+///   greater than 1200 or `name` is `,`), returns [`Error::Error`].
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `specifier` and `name` should both be null-terminated UTF-8 encoded
+///   strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_add_operator(
+    machine: &mut Machine,
+    priority: u32,
+    specifier: *const c_char,
+    name: *const c_char,
+) -> Error {
+    let specifier = unsafe { CStr::from_ptr(specifier) }
+        .to_str()
+        .expect("UTF-8 encoding");
+    let name = unsafe { CStr::from_ptr(name) }
+        .to_str()
+        .expect("UTF-8 encoding");
+
+    let goal = scryer_prolog::Term::Compound(
+        "op".into(),
+        vec![
+            scryer_prolog::Term::Integer(priority.into()),
+            scryer_prolog::Term::Atom(specifier.into()),
+            scryer_prolog::Term::Atom(name.into()),
+        ],
+    );
+    machine.run_goal(&goal)
+}
+
+/// Removes an operator, like `op/3` with priority 0.
+///
+/// # Errors
+///
+/// - If there is a query in progress in `machine`, returns [`Error::Busy`].
+/// - If the operator can't be removed, returns [`Error::Error`].
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `specifier` and `name` should both be null-terminated UTF-8 encoded
+///   strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_remove_operator(
+    machine: &mut Machine,
+    specifier: *const c_char,
+    name: *const c_char,
+) -> Error {
+    unsafe { scryer_machine_add_operator(machine, 0, specifier, name) }
+}
+
+/// Lists the current operators, like `current_op/3`.
+///
+/// On success updates `operators` with a pointer to a buffer containing
+/// pointers to terms of the form `op(Priority, Specifier, Name)`, and `len` to
+/// the number of terms in that buffer, like in [`scryer_term_unwrap_list`].
+///
+/// This buffer needs to be dropped with `scryer_list_drop`.
+///
+/// # Errors
+///
+/// If there is a query in progress in `machine`, returns [`Error::Busy`],
+/// updates `operators` to a null pointer and `len` to 0.
+///
+/// # Safety
+///
+/// `machine` should point to a [`Machine`] previously created with
+/// [`scryer_machine_builder_build`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_operators(
+    machine: &mut Machine,
+    operators: *mut *mut *mut Term,
+    len: *mut usize,
+) -> Error {
+    let operator = vec![var("P"), var("T"), var("N")];
+    let goal = compound(
+        "findall",
+        vec![
+            compound("op", operator.clone()),
+            compound("current_op", operator),
+            var("List"),
+        ],
+    );
+
+    let (error, operators_ptr, operators_len) = match machine.query_list(&goal, "List") {
+        Ok(list) => {
+            let (ptr, len) = terms_into_raw(list);
+            (Error::Success, ptr, len)
+        }
+        Err(error) => (error, std::ptr::null_mut(), 0),
+    };
+
+    unsafe { *operators = operators_ptr };
+    unsafe { *len = operators_len };
+
+    error
+}
+
+/// Stores a global variable in the blackboard, like `bb_put/2`.
+///
+/// The value isn't undone on backtracking, and stays until it's replaced by
+/// another call to this or to `bb_put/2`. It can be read from Prolog with
+/// `bb_get/2`. The [`Term`] is copied, so it still needs to be dropped
+/// separately.
+///
+/// The first call to this or to [`scryer_machine_bb_get`] loads
+/// `library(iso_ext)`, which defines the blackboard predicates. Nothing from it
+/// is imported into `user`.
+///
+/// # Errors
+///
+/// - If there is a query in progress in `machine`, returns [`Error::Busy`].
+/// - If the value can't be stored, returns [`Error::Error`].
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `key` should be a null-terminated UTF-8 encoded string.
+/// - `value` should point to a [`Term`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_bb_put(
+    machine: &mut Machine,
+    key: *const c_char,
+    value: &Term,
+) -> Error {
+    let key = unsafe { CStr::from_ptr(key) }
+        .to_str()
+        .expect("UTF-8 encoding");
+
+    match machine.use_library("iso_ext") {
+        Error::Success => {}
+        error => return error,
+    }
+
+    let goal = compound(
+        ":",
+        vec![
+            atom("iso_ext"),
+            compound("bb_put", vec![atom(key), value.0.clone()]),
+        ],
+    );
+    machine.run_goal(&goal)
+}
+
+/// Gets a global variable from the blackboard, like `bb_get/2`.
+///
+/// On success updates `value` with a pointer to a [`Term`].
+///
+/// # Errors
+///
+/// - If there is a query in progress in `machine`, returns [`Error::Busy`] and
+///   updates `value` to a null pointer.
+/// - If there is no global variable named `key`, returns [`Error::Error`] and
+///   updates `value` to a null pointer.
+///
+/// # Safety
+///
+/// - `machine` should point to a [`Machine`] previously created with
+///   [`scryer_machine_builder_build`].
+/// - `key` should be a null-terminated UTF-8 encoded string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_machine_bb_get(
+    machine: &mut Machine,
+    key: *const c_char,
+    value: *mut *mut Term,
+) -> Error {
+    let (error, value_ptr) = match machine.use_library("iso_ext") {
+        Error::Success => {
+            // SAFETY: The caller guarantees that `key` is a valid string.
+            let goal = compound("bb_get", vec![unsafe { atom_from_raw(key) }, var("Value")]);
+            let goal = compound(":", vec![atom("iso_ext"), goal]);
+            match machine.query_binding(&goal, "Value") {
+                Ok(term) => (Error::Success, Box::into_raw(Box::new(Term(term)))),
+                Err(error) => (error, std::ptr::null_mut()),
+            }
+        }
+        error => (error, std::ptr::null_mut()),
+    };
+
+    unsafe { *value = value_ptr };
+
+    error
+}
+
+// === QueryOptions methods ===
+
+/// Creates a [`QueryOptions`] without any limits.
+#[unsafe(no_mangle)]
+pub extern "C" fn scryer_query_options_new() -> Box<QueryOptions> {
+    Box::new(QueryOptions::default())
 }
 
-// === Machine methods ===
-
-/// Drops a [`Machine`].
+/// Drops a [`QueryOptions`].
 ///
 /// # Safety
 ///
-/// `machine` should point to a [`Machine`] previously created with
-/// [`scryer_machine_builder_build`].
+/// `query_options` should point to a [`QueryOptions`] previously created with
+/// [`scryer_query_options_new`].
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn scryer_machine_drop(machine: Box<Machine>) {
-    drop(machine)
+pub unsafe extern "C" fn scryer_query_options_drop(query_options: Box<QueryOptions>) {
+    drop(query_options)
 }
 
-/// Run a query from a string.
+/// Sets a wall-clock timeout for the query, in seconds.
 ///
-/// If no error occurs, `query_state` will be updated with a pointer to a
-/// [`QueryState`]. This [`Machine`] shoudn't be accessed again until that
-/// [`QueryState`] is dropped with `scryer_query_state_drop`.
+/// The query is run as with `call_with_time_limit(Seconds, once(Query))`, so
+/// with a timeout it has at most one leaf answer. When the time is up, the
+/// query throws `error(resource_error(time), call_with_time_limit/2)`.
+///
+/// The first query run with a timeout loads `library(time)` into its
+/// [`Machine`]. Nothing from it is imported into `user`, so it doesn't clash
+/// with the predicates of the program.
 ///
 /// # Errors
 ///
-/// Currently this function can't error, but this will probably change.
+/// If `seconds` isn't a positive number, returns [`Error::Error`] and the
+/// options aren't changed.
 ///
 /// # Safety
 ///
-/// - `machine` should point to a [`Machine`] previously created with
-///   [`scryer_machine_builder_build`].
-/// - `query` should be a null-terminated
-///   UTF-8 encoded string.
+/// `query_options` should point to a [`QueryOptions`] previously created with
+/// [`scryer_query_options_new`].
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn scryer_machine_run_query<'a>(
-    machine: &'a mut Machine,
-    query: *const c_char,
-    query_state: *mut *mut QueryState<'a>,
+pub unsafe extern "C" fn scryer_query_options_set_timeout(
+    query_options: &mut QueryOptions,
+    seconds: c_double,
 ) -> Error {
-    let query = unsafe { CStr::from_ptr(query) }.to_str().unwrap();
-
-    let query_state_box = Box::new(QueryState(machine.0.run_query(query)));
-    let query_state_ptr = Box::into_raw(query_state_box);
-    unsafe { *query_state = query_state_ptr };
-    Error::Success
+    if seconds.is_finite() && seconds > 0.0 {
+        query_options.timeout = Some(seconds);
+        Error::Success
+    } else {
+        Error::Error
+    }
 }
 
-/// Consults a module from a string.
+/// Sets a maximum number of inferences for the query.
+///
+/// The query is run as with `call_with_inference_limit/3`. When the limit is
+/// reached, the query throws
+/// `error(resource_error(inferences), call_with_inference_limit/3)`.
+///
+/// The first query run with an inference limit loads `library(iso_ext)` into
+/// its [`Machine`]. Nothing from it is imported into `user`, so it doesn't
+/// clash with the predicates of the program.
 ///
 /// # Errors
 ///
-/// Currently this function can't error, but this will probably change.
+/// If `limit` is 0, returns [`Error::Error`] and the options aren't changed.
 ///
 /// # Safety
 ///
-/// - `machine` should point to a [`Machine`] previously created with
-///   [`scryer_machine_builder_build`].
-/// - `module` and `program` should both be
-///   null-terminated UTF-8 encoded strings.
+/// `query_options` should point to a [`QueryOptions`] previously created with
+/// [`scryer_query_options_new`].
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn scryer_machine_consult_module_string(
-    machine: &mut Machine,
-    module: *const c_char,
-    program: *const c_char,
+pub unsafe extern "C" fn scryer_query_options_set_inference_limit(
+    query_options: &mut QueryOptions,
+    limit: u64,
 ) -> Error {
-    let module = unsafe { CStr::from_ptr(module) }
-        .to_str()
-        .expect("UTF-8 encoding");
-    let program = unsafe { CStr::from_ptr(program) }
-        .to_str()
-        .expect("UTF-8 encoding");
+    if limit > 0 {
+        query_options.inference_limit = Some(limit);
+        Error::Success
+    } else {
+        Error::Error
+    }
+}
 
-    machine.0.consult_module_string(module, program);
-    Error::Success
+/// Sets whether the leaf answers of the query include the attributes of its
+/// variables, as residual goals.
+///
+/// Attributes are what libraries like `library(clpz)`, `library(dif)` and
+/// `library(freeze)` attach to variables to constrain them. This API doesn't
+/// read the attributes themselves. Instead, for each named variable of the
+/// query, it gives the residual goals from `copy_term/3` of the term that
+/// variable is bound to, which are the goals the libraries use to describe
+/// their attributes.
+///
+/// The goals are given as `copy_term/3` gives them. Some libraries qualify
+/// them with their module, like `clpz:(X in 1..3)`, and then the module that
+/// owns the attribute is known. Others don't, like `dif(X, a)` or
+/// `freeze(X, true)`, and then the module isn't known. They can be unwrapped
+/// with [`scryer_leaf_answer_unwrap_attributes`].
+///
+/// # Safety
+///
+/// `query_options` should point to a [`QueryOptions`] previously created with
+/// [`scryer_query_options_new`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_query_options_set_attributes(
+    query_options: &mut QueryOptions,
+    attributes: bool,
+) {
+    query_options.attributes = attributes;
 }
 
 // === QueryState methods ===
 
 /// Drops a [`QueryState`].
 ///
+/// This ends the query, so its parent [`Machine`] can be used again.
+///
 /// # Safety
 ///
 /// `query_state` should point to a [`QueryState`] previously created with
@@ -218,26 +2156,63 @@ pub unsafe extern "C" fn scryer_query_state_next_answer(
     query_state: &mut QueryState,
     leaf_answer: *mut *mut LeafAnswer,
 ) -> Error {
-    let (error, leaf_answer_ptr) = query_state
-        .0
-        .next()
-        .map(|l| match l {
-            Ok(la) => (
-                Error::Success,
-                Box::into_raw(Box::new(LeafAnswer(LeafAnswerInner::Success(la)))),
-            ),
-            Err(error) => (
-                Error::Error,
-                Box::into_raw(Box::new(LeafAnswer(LeafAnswerInner::Error(error)))),
-            ),
-        })
-        .unwrap_or((Error::Success, std::ptr::null_mut()));
+    let (error, leaf_answer_ptr) = query_state.next_answer();
 
     unsafe { *leaf_answer = leaf_answer_ptr };
 
     error
 }
 
+/// Get the next leaf answer from the query in a background thread.
+///
+/// This returns immediately, and `callback` is called from the background
+/// thread with the result once it's ready, like it would be returned by
+/// [`scryer_query_state_next_answer`]. This is useful for hosts that run an
+/// event loop, which shouldn't be blocked by long running queries. To bound
+/// how long a query can take, use the limits in [`QueryOptions`].
+///
+/// Each call spawns a new thread that ends after calling `callback`, so this
+/// is meant for queries that take a while to answer. For quick queries the
+/// cost of spawning the thread dominates, and
+/// [`scryer_query_state_next_answer`] is better.
+///
+/// # Errors
+///
+/// If `callback` is a null pointer or the thread can't be spawned, returns
+/// [`Error::Error`] and `callback` is never called. In that case `query_state`
+/// can be used again right away.
+///
+/// # Safety
+///
+/// - `query_state` should point to a [`QueryState`] previously created with
+///   [`scryer_machine_run_query`].
+/// - If this succeeds, `query_state` shouldn't be used in any way until
+///   `callback` is called.
+/// - `callback` and `user_data` should be safe to use from another thread.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_query_state_next_answer_async(
+    query_state: &mut QueryState,
+    callback: NextAnswerCallback,
+    user_data: *mut c_void,
+) -> Error {
+    let Some(callback) = callback else {
+        return Error::Error;
+    };
+
+    let job = NextAnswerJob {
+        query_state,
+        callback,
+        user_data,
+    };
+    match std::thread::Builder::new()
+        .name("scryer-next-answer".into())
+        .spawn(move || job.run())
+    {
+        Ok(_) => Error::Success,
+        Err(_) => Error::Error,
+    }
+}
+
 // === LeafAnswer methods ===
 
 /// Drops a [`LeafAnswer`].
@@ -245,7 +2220,8 @@ pub unsafe extern "C" fn scryer_query_state_next_answer(
 /// # Safety
 ///
 /// `leaf_answer` should point to a [`LeafAnswer`] previously created with
-/// [`scryer_query_state_next_answer`].
+/// [`scryer_query_state_next_answer`], [`scryer_machine_query_all`] or
+/// [`scryer_machine_query_once`].
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn scryer_leaf_answer_drop(leaf_answer: Box<LeafAnswer>) {
     drop(leaf_answer)
@@ -256,7 +2232,8 @@ pub unsafe extern "C" fn scryer_leaf_answer_drop(leaf_answer: Box<LeafAnswer>) {
 /// # Safety
 ///
 /// `leaf_answer` should point to a [`LeafAnswer`] previously created with
-/// [`scryer_query_state_next_answer`].
+/// [`scryer_query_state_next_answer`], [`scryer_machine_query_all`] or
+/// [`scryer_machine_query_once`].
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn scryer_leaf_answer_kind(leaf_answer: &LeafAnswer) -> LeafAnswerKind {
     match &leaf_answer.0 {
@@ -282,7 +2259,8 @@ pub unsafe extern "C" fn scryer_leaf_answer_kind(leaf_answer: &LeafAnswer) -> Le
 /// # Safety
 ///
 /// `leaf_answer` should point to a [`LeafAnswer`] previously created with
-/// [`scryer_query_state_next_answer`].
+/// [`scryer_query_state_next_answer`], [`scryer_machine_query_all`] or
+/// [`scryer_machine_query_once`].
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn scryer_leaf_answer_unwrap_exception(
     leaf_answer: &LeafAnswer,
@@ -318,7 +2296,8 @@ pub unsafe extern "C" fn scryer_leaf_answer_unwrap_exception(
 /// # Safety
 ///
 /// `leaf_answer` should point to a [`LeafAnswer`] previously created with
-/// [`scryer_query_state_next_answer`].
+/// [`scryer_query_state_next_answer`], [`scryer_machine_query_all`] or
+/// [`scryer_machine_query_once`].
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn scryer_leaf_answer_unwrap_bindings(
     leaf_answer: &LeafAnswer,
@@ -340,6 +2319,61 @@ pub unsafe extern "C" fn scryer_leaf_answer_unwrap_bindings(
     error
 }
 
+/// Unwraps the attributes of a variable of the query from a [`LeafAnswer`].
+///
+/// On success updates `attributes` with a pointer to a buffer containing
+/// pointers to the residual goals of `variable`, as described in
+/// [`scryer_query_options_set_attributes`], and `len` to the number of goals in
+/// that buffer, like in [`scryer_term_unwrap_list`]. The buffer is empty if the
+/// variable has no attributes.
+///
+/// This buffer needs to be dropped with `scryer_list_drop`.
+///
+/// # Errors
+///
+/// - If the `LeafAnswer` is an exception or false, this returns
+///   [`Error::Error`], updates `attributes` to a null pointer and `len` to 0.
+/// - If `variable` isn't a named variable of the query, or attributes weren't
+///   requested with [`scryer_query_options_set_attributes`], this returns
+///   [`Error::Error`], updates `attributes` to a null pointer and `len` to 0.
+///
+/// # Safety
+///
+/// - `leaf_answer` should point to a [`LeafAnswer`] previously created with
+///   [`scryer_query_state_next_answer`], [`scryer_machine_query_all`] or
+///   [`scryer_machine_query_once`].
+/// - `variable` should be a null-terminated UTF-8 encoded string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_leaf_answer_unwrap_attributes(
+    leaf_answer: &LeafAnswer,
+    variable: *const c_char,
+    attributes: *mut *mut *mut Term,
+    len: *mut usize,
+) -> Error {
+    let variable = unsafe { CStr::from_ptr(variable) }
+        .to_str()
+        .expect("UTF-8 encoding");
+
+    let (error, attributes_ptr, attributes_len) =
+        match (&leaf_answer.0, leaf_answer.1.get(variable)) {
+            (
+                LeafAnswerInner::Success(
+                    scryer_prolog::LeafAnswer::True | scryer_prolog::LeafAnswer::LeafAnswer { .. },
+                ),
+                Some(goals),
+            ) => {
+                let (ptr, len) = terms_into_raw(goals.clone());
+                (Error::Success, ptr, len)
+            }
+            _ => (Error::Error, std::ptr::null_mut(), 0),
+        };
+
+    unsafe { *attributes = attributes_ptr };
+    unsafe { *len = attributes_len };
+
+    error
+}
+
 // === Bindings methods ===
 
 /// Drops a [`Bindings`].
@@ -400,6 +2434,207 @@ pub unsafe extern "C" fn scryer_term_drop(term: Box<Term>) {
     drop(term)
 }
 
+/// Creates an integer [`Term`].
+///
+/// `big_integer` is a null-terminated string with the decimal representation
+/// of the integer, so that arbitrary precision can be supported. On success
+/// updates `term` with a pointer to the new [`Term`].
+///
+/// # Errors
+///
+/// If `big_integer` isn't a valid integer, returns [`Error::Error`] and updates
+/// `term` to a null pointer.
+///
+/// # Safety
+///
+/// `big_integer` should be a null-terminated UTF-8 encoded string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_term_new_integer(
+    big_integer: *const c_char,
+    term: *mut *mut Term,
+) -> Error {
+    let big_integer = unsafe { CStr::from_ptr(big_integer) }
+        .to_str()
+        .expect("UTF-8 encoding");
+
+    let (error, term_ptr) = match big_integer.parse::<dashu::Integer>() {
+        Ok(int) => (
+            Error::Success,
+            Box::into_raw(Box::new(Term(scryer_prolog::Term::Integer(int)))),
+        ),
+        Err(_) => (Error::Error, std::ptr::null_mut()),
+    };
+
+    unsafe { *term = term_ptr };
+
+    error
+}
+
+/// Creates a rational [`Term`].
+///
+/// `numerator` and `denominator` are strings representing their values, like
+/// in [`scryer_term_new_integer`]. On success updates `term` with a pointer to
+/// the new [`Term`].
+///
+/// # Errors
+///
+/// If `numerator` isn't a valid integer or `denominator` isn't a valid
+/// positive integer, returns [`Error::Error`] and updates `term` to a null
+/// pointer.
+///
+/// # Safety
+///
+/// `numerator` and `denominator` should both be null-terminated UTF-8 encoded
+/// strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_term_new_rational(
+    numerator: *const c_char,
+    denominator: *const c_char,
+    term: *mut *mut Term,
+) -> Error {
+    let numerator = unsafe { CStr::from_ptr(numerator) }
+        .to_str()
+        .expect("UTF-8 encoding");
+    let denominator = unsafe { CStr::from_ptr(denominator) }
+        .to_str()
+        .expect("UTF-8 encoding");
+
+    let (error, term_ptr) = match (
+        numerator.parse::<dashu::Integer>(),
+        denominator.parse::<dashu::Natural>(),
+    ) {
+        (Ok(num), Ok(den)) if den != dashu::Natural::ZERO => (
+            Error::Success,
+            Box::into_raw(Box::new(Term(scryer_prolog::Term::Rational(
+                dashu::Rational::from_parts(num, den),
+            )))),
+        ),
+        _ => (Error::Error, std::ptr::null_mut()),
+    };
+
+    unsafe { *term = term_ptr };
+
+    error
+}
+
+/// Creates a float [`Term`].
+///
+/// On success updates `term` with a pointer to the new [`Term`].
+///
+/// # Errors
+///
+/// If `scryer_float` is infinite or NaN, returns [`Error::Error`] and updates
+/// `term` to a null pointer.
+///
+/// # Safety
+///
+/// `term` should be a valid pointer to write the result to.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_term_new_float(
+    scryer_float: c_double,
+    term: *mut *mut Term,
+) -> Error {
+    let (error, term_ptr) = if scryer_float.is_finite() {
+        (
+            Error::Success,
+            Box::into_raw(Box::new(Term(scryer_prolog::Term::Float(scryer_float)))),
+        )
+    } else {
+        (Error::Error, std::ptr::null_mut())
+    };
+
+    unsafe { *term = term_ptr };
+
+    error
+}
+
+/// Creates an atom [`Term`].
+///
+/// # Safety
+///
+/// `atom` should be a null-terminated UTF-8 encoded string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_term_new_atom(atom: *const c_char) -> Box<Term> {
+    let atom = unsafe { CStr::from_ptr(atom) }
+        .to_str()
+        .expect("UTF-8 encoding");
+
+    Box::new(Term(scryer_prolog::Term::Atom(atom.into())))
+}
+
+/// Creates a string [`Term`].
+///
+/// # Safety
+///
+/// `string` should be a null-terminated UTF-8 encoded string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_term_new_string(string: *const c_char) -> Box<Term> {
+    let string = unsafe { CStr::from_ptr(string) }
+        .to_str()
+        .expect("UTF-8 encoding");
+
+    Box::new(Term(scryer_prolog::Term::String(string.into())))
+}
+
+/// Creates a list [`Term`].
+///
+/// The terms in `term_list` are copied, so they still need to be dropped
+/// separately.
+///
+/// # Safety
+///
+/// `term_list` should point to a buffer containing `len` pointers to terms.
+/// It can be a null pointer if `len` is 0.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_term_new_list(term_list: *const &Term, len: usize) -> Box<Term> {
+    // SAFETY: The caller guarantees that `term_list` points to `len` terms.
+    let term_list = unsafe { terms_from_raw(term_list, len) };
+
+    Box::new(Term(scryer_prolog::Term::List(term_list)))
+}
+
+/// Creates a compound [`Term`].
+///
+/// The terms in `args` are copied, so they still need to be dropped
+/// separately.
+///
+/// # Safety
+///
+/// - `functor` should be a null-terminated UTF-8 encoded string.
+/// - `args` should point to a buffer containing `len` pointers to terms.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_term_new_compound(
+    functor: *const c_char,
+    args: *const &Term,
+    len: usize,
+) -> Box<Term> {
+    let functor = unsafe { CStr::from_ptr(functor) }
+        .to_str()
+        .expect("UTF-8 encoding");
+
+    // SAFETY: The caller guarantees that `args` points to `len` terms.
+    let args = unsafe { terms_from_raw(args, len) };
+
+    Box::new(Term(scryer_prolog::Term::Compound(functor.into(), args)))
+}
+
+/// Creates a variable [`Term`].
+///
+/// Variables with the same name in the same term are the same variable. This
+/// includes `_`, which isn't an anonymous variable here.
+///
+/// # Safety
+///
+/// `variable` should be a null-terminated UTF-8 encoded string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_term_new_variable(variable: *const c_char) -> Box<Term> {
+    let variable = unsafe { CStr::from_ptr(variable) }
+        .to_str()
+        .expect("UTF-8 encoding");
+
+    Box::new(Term(scryer_prolog::Term::Var(variable.into())))
+}
+
 /// Gets the kind of a [`Term`].
 ///
 /// # Safety
@@ -721,8 +2956,181 @@ pub unsafe extern "C" fn scryer_string_drop(string: *mut c_char) {
 /// # Safety
 ///
 /// `list` should be a list previously created with [`scryer_term_unwrap_list`],
-/// and `len` should be it's length.
+/// [`scryer_term_unwrap_compound`], [`scryer_machine_predicates`],
+/// [`scryer_machine_predicate_clauses`], [`scryer_machine_operators`] or
+/// [`scryer_leaf_answer_unwrap_attributes`], and `len` should be it's length.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn scryer_list_drop(list: *mut *mut Term, len: usize) {
     drop(unsafe { Vec::from_raw_parts(list, len, len) })
 }
+
+/// Drop a previously allocated list of leaf answers.
+///
+/// This only frees the memory for the list itself. The leaf answers it
+/// contains should be dropped first separatelly.
+///
+/// # Safety
+///
+/// `list` should be a list previously created with
+/// [`scryer_machine_query_all`], and `len` should be it's length.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn scryer_leaf_answer_list_drop(list: *mut *mut LeafAnswer, len: usize) {
+    drop(unsafe { Vec::from_raw_parts(list, len, len) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a machine with some facts.
+    fn new_machine() -> Box<Machine> {
+        let mut machine = std::ptr::null_mut();
+        let mut exception = std::ptr::null_mut();
+        let error = unsafe {
+            scryer_machine_builder_build(scryer_machine_builder_new(), &mut machine, &mut exception)
+        };
+        assert!(matches!(error, Error::Success));
+        let mut machine = unsafe { Box::from_raw(machine) };
+
+        let program = c"a(1).\na(2).\na(3).\n";
+        let error = unsafe {
+            scryer_machine_consult_module_string(
+                &mut machine,
+                c"test_module".as_ptr(),
+                program.as_ptr(),
+            )
+        };
+        assert!(matches!(error, Error::Success));
+
+        machine
+    }
+
+    /// Gets the answers of a query on a machine built with [`new_machine`], and
+    /// drops it.
+    fn run_machine(mut machine: Box<Machine>) -> Vec<String> {
+        let mut answers = std::ptr::null_mut();
+        let mut len = 0;
+        let error = unsafe {
+            scryer_machine_query_all(
+                &mut machine,
+                c"a(A).".as_ptr(),
+                None,
+                0,
+                &mut answers,
+                &mut len,
+            )
+        };
+        assert!(matches!(error, Error::Success));
+
+        let answers = unsafe { Vec::from_raw_parts(answers, len, len) };
+        let values = answers
+            .into_iter()
+            .map(|answer| {
+                let answer = unsafe { Box::from_raw(answer) };
+                match &answer.0 {
+                    LeafAnswerInner::Success(scryer_prolog::LeafAnswer::LeafAnswer {
+                        bindings,
+                        ..
+                    }) => bindings["A"].clone(),
+                    _ => panic!("expected bindings"),
+                }
+            })
+            .map(|term| match term {
+                scryer_prolog::Term::Integer(int) => int.to_string(),
+                _ => panic!("expected an integer"),
+            })
+            .collect();
+
+        assert!(matches!(
+            unsafe { scryer_machine_drop(machine) },
+            Error::Success
+        ));
+        values
+    }
+
+    #[test]
+    fn hidden_prefix_avoids_query_variables() {
+        let query_options = QueryOptions {
+            attributes: true,
+            ..Default::default()
+        };
+
+        let (query, prefix) = query_options
+            .wrap_query("a(ScryerCApi_X, ScryerCApiY). % ScryerCApi__Z.")
+            .unwrap();
+        assert_eq!(prefix, "ScryerCApi__");
+        assert!(
+            query
+                .ends_with("'ScryerCApi_X'-ScryerCApi__Goals0, 'ScryerCApiY'-ScryerCApi__Goals1].")
+        );
+    }
+
+    #[test]
+    fn machines_on_threads() {
+        let threads: Vec<_> = (0..4)
+            .map(|_| std::thread::spawn(|| run_machine(new_machine())))
+            .collect();
+
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), ["1", "2", "3"]);
+        }
+    }
+
+    #[test]
+    fn busy_while_query_in_progress() {
+        let machine = Box::into_raw(new_machine());
+
+        let mut query_state = std::ptr::null_mut();
+        let error =
+            unsafe { scryer_machine_run_query(&mut *machine, c"a(A).".as_ptr(), &mut query_state) };
+        assert!(matches!(error, Error::Success));
+
+        let mut other_query_state = std::ptr::null_mut();
+        let error = unsafe {
+            scryer_machine_run_query(&mut *machine, c"a(A).".as_ptr(), &mut other_query_state)
+        };
+        assert!(matches!(error, Error::Busy));
+        assert!(other_query_state.is_null());
+
+        let error = unsafe {
+            scryer_machine_consult_module_string(
+                &mut *machine,
+                c"test_module".as_ptr(),
+                c"a(4).\n".as_ptr(),
+            )
+        };
+        assert!(matches!(error, Error::Busy));
+
+        let clause = Term(compound("a", vec![scryer_prolog::Term::Integer(4.into())]));
+        let mut exception = std::ptr::null_mut();
+        let error = unsafe {
+            scryer_machine_assertz(
+                &mut *machine,
+                c"test_module".as_ptr(),
+                &clause,
+                &mut exception,
+            )
+        };
+        assert!(matches!(error, Error::Busy));
+        assert!(exception.is_null());
+
+        // The machine isn't freed, so it can still be used below.
+        let error = unsafe { scryer_machine_drop(Box::from_raw(machine)) };
+        assert!(matches!(error, Error::Busy));
+
+        unsafe { scryer_query_state_drop(Box::from_raw(query_state)) };
+
+        assert_eq!(
+            run_machine(unsafe { Box::from_raw(machine) }),
+            ["1", "2", "3"]
+        );
+    }
+
+    #[test]
+    fn machine_moves_between_threads() {
+        let machine = new_machine();
+        let thread = std::thread::spawn(move || run_machine(machine));
+
+        assert_eq!(thread.join().unwrap(), ["1", "2", "3"]);
+    }
+}
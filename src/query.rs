@@ -0,0 +1,164 @@
+//! Scanning of query text.
+//!
+//! This only knows enough of the Prolog syntax to skip quoted items and
+//! comments, which is what's needed to wrap queries in other goals.
+
+/// Gets the goal of `query`, without its end token.
+///
+/// If `query` doesn't end with an end token, it's returned as is.
+pub(crate) fn goal_text(query: &str) -> &str {
+    let chars: Vec<(usize, char)> = code_chars(query).collect();
+
+    for (i, &(pos, c)) in chars.iter().enumerate() {
+        let is_end = c == '.'
+            && chars
+                .get(i + 1)
+                .is_none_or(|&(_, next)| next.is_whitespace())
+            && i.checked_sub(1)
+                .is_none_or(|prev| !is_symbol_char(chars[prev].1));
+
+        if is_end {
+            let rest_is_layout = chars[i + 1..].iter().all(|&(_, c)| c.is_whitespace());
+            return if rest_is_layout { &query[..pos] } else { query };
+        }
+    }
+
+    query
+}
+
+/// Gets the names of the named variables in `goal`, in order of appearance.
+///
+/// Variables starting with `_` are left out, since they don't appear in
+/// answers.
+pub(crate) fn variable_names(goal: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut name: Option<String> = None;
+    let mut prev = ' ';
+
+    // The trailing space ends a name at the end of `goal`.
+    for c in code_chars(goal).map(|(_, c)| c).chain([' ']) {
+        let is_alphanumeric = c == '_' || c.is_alphanumeric();
+        match name.take() {
+            Some(mut n) if is_alphanumeric => {
+                n.push(c);
+                name = Some(n);
+            }
+            Some(n) if !n.starts_with('_') && !names.contains(&n) => names.push(n),
+            Some(_) => {}
+            // A variable name starts with `_` or an uppercase letter.
+            None if (c == '_' || c.is_uppercase()) && !(prev == '_' || prev.is_alphanumeric()) => {
+                name = Some(c.to_string());
+            }
+            None => {}
+        }
+        prev = c;
+    }
+
+    names
+}
+
+fn is_symbol_char(c: char) -> bool {
+    "+-*/\\^<>=~:.?@#&$".contains(c)
+}
+
+/// Iterates over the characters of `text` that are Prolog code, with their
+/// byte positions.
+///
+/// Quoted items and comments are given as a single space.
+fn code_chars(text: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+    let mut chars = text.char_indices().peekable();
+    let mut prev = None;
+
+    std::iter::from_fn(move || {
+        let (pos, c) = chars.next()?;
+
+        let item = match c {
+            '%' => {
+                while chars.next_if(|&(_, c)| c != '\n').is_some() {}
+                (pos, ' ')
+            }
+            '/' if chars.next_if(|&(_, c)| c == '*').is_some() => {
+                let mut last = ' ';
+                for (_, c) in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+                (pos, ' ')
+            }
+            '\'' if prev == Some('0') => {
+                // A character code like `0'a`, `0'\n` or `0'''`.
+                match chars.next() {
+                    Some((_, '\\')) => {
+                        chars.next();
+                    }
+                    Some((_, '\'')) => {
+                        chars.next_if(|&(_, c)| c == '\'');
+                    }
+                    _ => {}
+                }
+                (pos, '0')
+            }
+            '\'' | '"' | '`' => {
+                while let Some((_, next)) = chars.next() {
+                    if next == '\\' {
+                        chars.next();
+                    } else if next == c && chars.next_if(|&(_, c2)| c2 == c).is_none() {
+                        break;
+                    }
+                }
+                (pos, ' ')
+            }
+            c => (pos, c),
+        };
+
+        // A `0` only starts a character code if it starts a number.
+        prev = match (prev, item.1) {
+            (Some(p), '0') if p == '_' || p.is_alphanumeric() => Some('a'),
+            (_, c) => Some(c),
+        };
+
+        Some(item)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_end_token() {
+        assert_eq!(goal_text("a(X)."), "a(X)");
+        assert_eq!(goal_text("a(X).\n"), "a(X)");
+        assert_eq!(goal_text("a(X)"), "a(X)");
+    }
+
+    #[test]
+    fn strips_end_token_before_comments() {
+        assert_eq!(goal_text("a(X). % comment."), "a(X)");
+        assert_eq!(goal_text("a(X). /* comment. */"), "a(X)");
+        assert_eq!(goal_text("a(X) % comment."), "a(X) % comment.");
+    }
+
+    #[test]
+    fn ignores_dots_in_quoted_items() {
+        assert_eq!(goal_text("X = 'a. b'."), "X = 'a. b'");
+        assert_eq!(goal_text("X = \"a. \\\" b\"."), "X = \"a. \\\" b\"");
+        assert_eq!(goal_text("X = 0'. ."), "X = 0'. ");
+    }
+
+    #[test]
+    fn finds_variable_names() {
+        assert_eq!(variable_names("a(X, Y), b(X, _Z, _)"), ["X", "Y"]);
+        assert_eq!(variable_names("X = 'Y', Z = \"W\" % V"), ["X", "Z"]);
+        assert_eq!(variable_names("X = 0'A, Y is 1E3"), ["X", "Y"]);
+        assert_eq!(variable_names("foo(Bar)"), ["Bar"]);
+    }
+
+    #[test]
+    fn keeps_symbol_atoms() {
+        assert_eq!(goal_text("X =.. [f, a]."), "X =.. [f, a]");
+        assert_eq!(goal_text("X = '.'."), "X = '.'");
+    }
+}
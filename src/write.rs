@@ -0,0 +1,241 @@
+//! Writing of terms as Prolog text.
+//!
+//! Terms are written in canonical form, so that reading them back doesn't
+//! depend on the operator table or on flags like `double_quotes`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use scryer_prolog::Term;
+
+/// Writes `term` as Prolog text.
+///
+/// Variables with the same name are written as the same variable. Variables
+/// whose names can't be written as they are, like `_` or `x`, are renamed to
+/// names that aren't used elsewhere in `term`.
+pub(crate) fn write_term(term: &Term) -> String {
+    let mut names = BTreeSet::new();
+    variable_names(term, &mut names);
+
+    let mut writer = Writer {
+        out: String::new(),
+        names,
+        renamed: BTreeMap::new(),
+        next_name: 0,
+    };
+    writer.write_term(term);
+    writer.out
+}
+
+/// Collects the names of the variables in `term`.
+fn variable_names<'a>(term: &'a Term, names: &mut BTreeSet<&'a str>) {
+    match term {
+        Term::List(args) | Term::Compound(_, args) => {
+            for arg in args {
+                variable_names(arg, names);
+            }
+        }
+        Term::Var(var) => {
+            names.insert(var);
+        }
+        _ => {}
+    }
+}
+
+/// Whether `var` can be written as a variable name as it is.
+fn is_var_name(var: &str) -> bool {
+    let mut chars = var.chars();
+    var != "_"
+        && chars.next().is_some_and(|c| c == '_' || c.is_uppercase())
+        && chars.all(|c| c == '_' || c.is_alphanumeric())
+}
+
+/// Writes a term, keeping track of the variables it renames.
+struct Writer<'a> {
+    out: String,
+    /// The names of all the variables in the term being written.
+    names: BTreeSet<&'a str>,
+    /// The new names of the variables that had to be renamed.
+    renamed: BTreeMap<&'a str, String>,
+    /// The number of the next name to try when renaming a variable.
+    next_name: usize,
+}
+
+impl<'a> Writer<'a> {
+    fn write_term(&mut self, term: &'a Term) {
+        let out = &mut self.out;
+        match term {
+            Term::Integer(int) => out.push_str(&int.to_string()),
+            Term::Rational(rational) => {
+                let (num, den) = rational.clone().into_parts();
+                out.push_str(&format!("{num}r{den}"));
+            }
+            Term::Float(float) => write_float(*float, out),
+            Term::Atom(atom) => write_atom(atom, out),
+            Term::String(string) => {
+                // Strings are lists of characters, so this is the same term
+                // regardless of the value of `double_quotes`.
+                out.push('[');
+                for (i, c) in string.chars().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    write_atom(c.encode_utf8(&mut [0; 4]), out);
+                }
+                out.push(']');
+            }
+            Term::List(list) => {
+                out.push('[');
+                self.write_args(list);
+                self.out.push(']');
+            }
+            Term::Compound(functor, args) if args.is_empty() => write_atom(functor, out),
+            Term::Compound(functor, args) => {
+                write_atom(functor, out);
+                out.push('(');
+                self.write_args(args);
+                self.out.push(')');
+            }
+            Term::Var(var) if is_var_name(var) => out.push_str(var),
+            Term::Var(var) => {
+                if !self.renamed.contains_key(var.as_str()) {
+                    let name = loop {
+                        let name = format!("_V{}", self.next_name);
+                        self.next_name += 1;
+                        if !self.names.contains(name.as_str()) {
+                            break name;
+                        }
+                    };
+                    self.renamed.insert(var, name);
+                }
+                self.out.push_str(&self.renamed[var.as_str()]);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn write_args(&mut self, args: &'a [Term]) {
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+                self.out.push(',');
+            }
+            self.write_term(arg);
+        }
+    }
+}
+
+fn write_float(float: f64, out: &mut String) {
+    // `Debug` always gives a fractional part, except when using an exponent.
+    let repr = format!("{float:?}");
+    match repr.split_once('e') {
+        Some((mantissa, exponent)) if !mantissa.contains('.') => {
+            out.push_str(&format!("{mantissa}.0e{exponent}"));
+        }
+        _ => out.push_str(&repr),
+    }
+}
+
+fn write_atom(atom: &str, out: &mut String) {
+    let mut chars = atom.chars();
+    let is_letter_digit = chars.next().is_some_and(|c| c.is_lowercase())
+        && chars.all(|c| c == '_' || c.is_alphanumeric());
+
+    if is_letter_digit || matches!(atom, "[]" | "{}" | "!") {
+        out.push_str(atom);
+        return;
+    }
+
+    out.push('\'');
+    for c in atom.chars() {
+        match c {
+            '\'' => out.push_str("\\'"),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => out.push_str(&format!("\\x{:x}\\", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('\'');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atom(name: &str) -> Term {
+        Term::Atom(name.into())
+    }
+
+    fn var(name: &str) -> Term {
+        Term::Var(name.into())
+    }
+
+    fn compound(functor: &str, args: Vec<Term>) -> Term {
+        Term::Compound(functor.into(), args)
+    }
+
+    #[test]
+    fn quotes_atoms() {
+        assert_eq!(write_term(&atom("foo_Bar1")), "foo_Bar1");
+        assert_eq!(write_term(&atom("it's")), "'it\\'s'");
+        assert_eq!(write_term(&atom("a\\b")), "'a\\\\b'");
+        assert_eq!(write_term(&atom("Foo")), "'Foo'");
+        assert_eq!(write_term(&atom("_foo")), "'_foo'");
+        assert_eq!(write_term(&atom("")), "''");
+        assert_eq!(write_term(&atom("a\nb")), "'a\\nb'");
+    }
+
+    #[test]
+    fn keeps_solo_atoms() {
+        assert_eq!(write_term(&atom("[]")), "[]");
+        assert_eq!(write_term(&atom("{}")), "{}");
+        assert_eq!(write_term(&atom("!")), "!");
+    }
+
+    #[test]
+    fn quotes_symbol_atoms() {
+        assert_eq!(write_term(&atom("-")), "'-'");
+        assert_eq!(write_term(&atom(",")), "','");
+        assert_eq!(write_term(&atom("|")), "'|'");
+        assert_eq!(
+            write_term(&compound(",", vec![atom("a"), atom("b")])),
+            "','(a,b)"
+        );
+    }
+
+    #[test]
+    fn writes_numbers() {
+        let minus_one = Term::Integer((-1).into());
+        assert_eq!(
+            write_term(&compound("-", vec![minus_one.clone(), minus_one])),
+            "'-'(-1,-1)"
+        );
+        assert_eq!(write_term(&Term::Float(1.5)), "1.5");
+        assert_eq!(write_term(&Term::Float(1e100)), "1.0e100");
+        assert_eq!(write_term(&Term::Float(-2.5e-10)), "-2.5e-10");
+        let rational = dashu::Rational::from_parts((-1).into(), 3u8.into());
+        assert_eq!(write_term(&Term::Rational(rational)), "-1r3");
+    }
+
+    #[test]
+    fn writes_strings_as_lists_of_chars() {
+        assert_eq!(write_term(&Term::String("aB'".into())), "[a,'B','\\'']");
+        assert_eq!(write_term(&Term::String("".into())), "[]");
+    }
+
+    #[test]
+    fn keeps_variable_names() {
+        let term = compound("f", vec![var("X"), var("_Y"), var("X")]);
+        assert_eq!(write_term(&term), "f(X,_Y,X)");
+    }
+
+    #[test]
+    fn renames_variables() {
+        let term = compound("f", vec![var("_"), var("x"), var("_"), var("x")]);
+        assert_eq!(write_term(&term), "f(_V0,_V1,_V0,_V1)");
+
+        let term = compound("f", vec![var("x"), var("_V0"), var("_x"), var("y")]);
+        assert_eq!(write_term(&term), "f(_V1,_V0,_x,_V2)");
+    }
+}